@@ -0,0 +1,71 @@
+use nalgebra_glm::Vec3;
+
+// Caja alineada a los ejes, usada por la BVH para descartar objetos rápido
+#[derive(Debug, Clone, Copy)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    pub fn new(min: Vec3, max: Vec3) -> Self {
+        Aabb { min, max }
+    }
+
+    // Caja vacía: cualquier unión con ella devuelve la otra caja
+    pub fn empty() -> Self {
+        Aabb {
+            min: Vec3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY),
+            max: Vec3::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY),
+        }
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb {
+            min: self.min.inf(&other.min),
+            max: self.max.sup(&other.max),
+        }
+    }
+
+    pub fn grow(&mut self, point: &Vec3) {
+        self.min = self.min.inf(point);
+        self.max = self.max.sup(point);
+    }
+
+    pub fn centroid(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn surface_area(&self) -> f32 {
+        let extent = self.max - self.min;
+        if extent.x < 0.0 || extent.y < 0.0 || extent.z < 0.0 {
+            return 0.0;
+        }
+        2.0 * (extent.x * extent.y + extent.y * extent.z + extent.z * extent.x)
+    }
+
+    // Prueba de slabs. Devuelve la distancia de entrada si el rayo toca la caja antes de max_distance
+    pub fn hit(&self, origin: &Vec3, inv_direction: &Vec3, max_distance: f32) -> Option<f32> {
+        let mut t_enter: f32 = 0.0;
+        let mut t_exit = max_distance;
+
+        for axis in 0..3 {
+            let t0 = (self.min[axis] - origin[axis]) * inv_direction[axis];
+            let t1 = (self.max[axis] - origin[axis]) * inv_direction[axis];
+            let (near, far) = if t0 < t1 { (t0, t1) } else { (t1, t0) };
+
+            // Los NaN (rayo paralelo sobre el plano) no modifican el intervalo
+            if near > t_enter {
+                t_enter = near;
+            }
+            if far < t_exit {
+                t_exit = far;
+            }
+            if t_enter > t_exit {
+                return None;
+            }
+        }
+
+        Some(t_enter)
+    }
+}
@@ -0,0 +1,86 @@
+use crate::color::Color;
+use crate::framebuffer::Framebuffer;
+use crate::scene_file::RenderSettings;
+use crate::tonemap::tone_map;
+
+// Suma de las muestras de un píxel ponderadas por el filtro. Se guarda aparte de Color
+// porque los pesos negativos de Mitchell no deben recortarse antes de normalizar.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SampleSum {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub weight: f32,
+}
+
+impl SampleSum {
+    pub fn add(&mut self, color: Color, weight: f32) {
+        self.r += color.r * weight;
+        self.g += color.g * weight;
+        self.b += color.b * weight;
+        self.weight += weight;
+    }
+
+    pub fn merge(&mut self, other: &SampleSum) {
+        self.r += other.r;
+        self.g += other.g;
+        self.b += other.b;
+        self.weight += other.weight;
+    }
+
+    // Promedio en espacio lineal
+    pub fn resolve(&self) -> Color {
+        if self.weight.abs() <= 1e-6 {
+            return Color::black();
+        }
+        Color::rgb(
+            (self.r / self.weight).max(0.0),
+            (self.g / self.weight).max(0.0),
+            (self.b / self.weight).max(0.0),
+        )
+    }
+}
+
+// Buffer de acumulación que acompaña al Framebuffer: cada pasada suma muestras nuevas
+// mientras la vista no cambie, y se reinicia cuando cambia la cámara, las luces o la escena.
+pub struct Accumulator {
+    width: usize,
+    sums: Vec<SampleSum>,
+    passes: u64,
+}
+
+impl Accumulator {
+    pub fn new(width: usize, height: usize) -> Self {
+        Accumulator {
+            width,
+            sums: vec![SampleSum::default(); width * height],
+            passes: 0,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.sums.fill(SampleSum::default());
+        self.passes = 0;
+    }
+
+    // Número de pasadas acumuladas; también sirve de semilla para la siguiente
+    pub fn passes(&self) -> u64 {
+        self.passes
+    }
+
+    pub fn add_pass(&mut self, pass: &[SampleSum]) {
+        for (sum, sample) in self.sums.iter_mut().zip(pass) {
+            sum.merge(sample);
+        }
+        self.passes += 1;
+    }
+
+    // Escribe el promedio acumulado en el framebuffer, ya con tone mapping
+    pub fn resolve_into(&self, framebuffer: &mut Framebuffer, settings: &RenderSettings) {
+        for (i, sum) in self.sums.iter().enumerate() {
+            let color = tone_map(sum.resolve(), settings.exposure, settings.tone_mapping);
+            framebuffer.set_current_color(color.to_hex());
+            framebuffer.point(i % self.width, i / self.width);
+        }
+    }
+}
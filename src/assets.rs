@@ -0,0 +1,164 @@
+use image::RgbaImage;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::texture::{height_to_normal_map, load_image, MipChain, Texture};
+
+// Las texturas de color cuadradas o rectangulares con lados potencia de dos hasta este
+// tamaño (las de bloques) se empaquetan en el atlas; las demás quedan sueltas.
+const ATLAS_MAX_TILE: u32 = 256;
+
+// Carga cada imagen una sola vez y entrega handles compartidos. Las texturas de bloques
+// van a un atlas común, cada una en su celda, así agregar tipos de bloque no duplica memoria.
+pub struct AssetCache {
+    pending: Vec<(String, RgbaImage)>,         // Imágenes de color que esperan al atlas
+    textures: HashMap<(String, bool), Texture>,  // (ruta, sRGB) -> handle
+    height_maps: HashMap<String, Texture>,       // Ruta -> mapa de normales derivado
+}
+
+// Misma ruta escrita de formas distintas (./a.png, dir/../a.png) es el mismo archivo
+fn cache_key(path: &str) -> String {
+    std::fs::canonicalize(path)
+        .map(|p| p.to_string_lossy().into_owned())
+        .unwrap_or_else(|_| path.to_string())
+}
+
+fn fits_atlas(image: &RgbaImage) -> bool {
+    let (width, height) = image.dimensions();
+    width.is_power_of_two() && height.is_power_of_two() && width.max(height) <= ATLAS_MAX_TILE
+}
+
+impl AssetCache {
+    pub fn new() -> Self {
+        AssetCache {
+            pending: Vec::new(),
+            textures: HashMap::new(),
+            height_maps: HashMap::new(),
+        }
+    }
+
+    // Lee una textura de color que se quiere en el atlas; no queda disponible hasta build_atlas
+    pub fn preload(&mut self, path: &str) -> Result<(), String> {
+        let key = cache_key(path);
+        if self.textures.contains_key(&(key.clone(), true)) || self.pending.iter().any(|(k, _)| *k == key) {
+            return Ok(());
+        }
+
+        let image = load_image(path)?;
+        if fits_atlas(&image) {
+            self.pending.push((key, image));
+        } else {
+            self.textures.insert((key, true), Texture::from_image(&image, true));
+        }
+        Ok(())
+    }
+
+    // Empaqueta las imágenes precargadas en estantes, de la más grande a la más chica, cada
+    // una en un cuadrado de su lado mayor. Como los lados son potencias de dos y van
+    // decreciendo, cada celda empieza en un múltiplo de su tamaño y los mipmaps no mezclan
+    // vecinas. El atlas crece con la suma de las texturas, no con la más grande.
+    pub fn build_atlas(&mut self) {
+        if self.pending.is_empty() {
+            return;
+        }
+
+        let cell = |image: &RgbaImage| image.width().max(image.height());
+        self.pending.sort_by_key(|(_, image)| std::cmp::Reverse(cell(image)));
+
+        // Ancho potencia de dos que deja el atlas más o menos cuadrado
+        let area: u64 = self.pending.iter().map(|(_, image)| (cell(image) as u64).pow(2)).sum();
+        let width = cell(&self.pending[0].1).max((area as f64).sqrt().ceil() as u32).next_power_of_two();
+
+        let mut positions = Vec::with_capacity(self.pending.len());
+        let (mut x, mut y, mut shelf_height) = (0, 0, 0);
+        for (_, image) in &self.pending {
+            let size = cell(image);
+            if x + size > width {
+                x = 0;
+                y += shelf_height;
+                shelf_height = 0;
+            }
+            // La primera celda de cada estante es la más alta
+            shelf_height = shelf_height.max(size);
+            positions.push((x, y));
+            x += size;
+        }
+
+        let mut atlas = RgbaImage::new(width, y + shelf_height);
+        let mut placements = Vec::with_capacity(self.pending.len());
+        for ((key, image), (x, y)) in self.pending.drain(..).zip(positions) {
+            image::imageops::replace(&mut atlas, &image, x as i64, y as i64);
+            placements.push((key, x, y, image.width(), image.height()));
+        }
+
+        let chain = Arc::new(MipChain::from_image(&atlas, true));
+        for (key, x, y, width, height) in placements {
+            self.textures.insert((key, true), Texture::region(&chain, x, y, width, height));
+        }
+    }
+
+    // Handle de la textura; si no estaba precargada se lee ahora y queda suelta
+    pub fn texture(&mut self, path: &str, srgb: bool) -> Result<Texture, String> {
+        let key = (cache_key(path), srgb);
+        if let Some(texture) = self.textures.get(&key) {
+            return Ok(texture.clone());
+        }
+
+        let texture = Texture::from_image(&load_image(path)?, srgb);
+        self.textures.insert(key, texture.clone());
+        Ok(texture)
+    }
+
+    // Mapa de alturas convertido a mapa de normales (convención OpenGL), leído una sola vez
+    pub fn height_map(&mut self, path: &str) -> Result<Texture, String> {
+        let key = cache_key(path);
+        if let Some(texture) = self.height_maps.get(&key) {
+            return Ok(texture.clone());
+        }
+
+        let normals = height_to_normal_map(&load_image(path)?);
+        let texture = Texture::from_image(&normals, false);
+        self.height_maps.insert(key, texture.clone());
+        Ok(texture)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{Rgba, RgbaImage};
+
+    use super::AssetCache;
+    use crate::texture::{TextureFilter, WrapMode};
+
+    // Color puro distinto para cada textura, con canales 0 o 255 para que el sRGB no los altere
+    fn solid(size: u32, index: usize) -> RgbaImage {
+        let bit = |b: usize| if index & b != 0 { 255 } else { 0 };
+        RgbaImage::from_pixel(size, size, Rgba([bit(1), bit(2), bit(4), if index & 8 != 0 { 255 } else { 128 }]))
+    }
+
+    #[test]
+    fn atlas_keeps_mixed_sizes_apart() {
+        let sizes = [16, 256, 16, 64, 16, 32, 16, 16, 64, 16, 32, 16, 16, 16, 16, 16];
+        let mut assets = AssetCache::new();
+        for (index, &size) in sizes.iter().enumerate() {
+            assets.pending.push((index.to_string(), solid(size, index)));
+        }
+        assets.build_atlas();
+
+        for (index, &size) in sizes.iter().enumerate() {
+            let texture = assets.texture(&index.to_string(), true).unwrap();
+            assert_eq!((texture.width, texture.height), (size, size));
+
+            let expected = solid(1, index).get_pixel(0, 0).0.map(|c| c as f32 / 255.0);
+            // Esquinas con el texel más cercano y el mipmap más chico de la celda: ninguno
+            // debe ver el color de otra textura
+            for (u, v, footprint) in [(0.01, 0.01, 0.0), (0.99, 0.99, 0.0), (0.5, 0.5, 1.0)] {
+                let filter = if footprint > 0.0 { TextureFilter::Trilinear } else { TextureFilter::Nearest };
+                let texel = texture.sample(u, v, footprint, filter, WrapMode::Repeat);
+                for channel in 0..4 {
+                    assert!((texel[channel] - expected[channel]).abs() < 1e-3, "textura {}: {:?}", index, texel);
+                }
+            }
+        }
+    }
+}
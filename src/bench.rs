@@ -0,0 +1,110 @@
+use nalgebra_glm::{Vec3, normalize};
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::bvh::Bvh;
+use crate::camera::Camera;
+use crate::color::Color;
+use crate::cube::Cube;
+use crate::material::Material;
+use crate::ray::{Ray, RayCone, RayKind};
+use crate::ray_intersect::{Intersect, RayIntersect};
+
+const BENCH_WIDTH: usize = 160;
+const BENCH_HEIGHT: usize = 120;
+
+// Recorre todos los objetos, como se hacía antes de la BVH
+fn linear_closest_hit<'a>(objects: &'a [Cube], ray: &Ray) -> Intersect<'a> {
+    let mut intersect = Intersect::empty();
+    let mut zbuffer = f32::INFINITY;
+
+    for (object_id, object) in objects.iter().enumerate() {
+        let i = object.ray_intersect(ray);
+        if i.is_intersecting && i.distance < zbuffer {
+            zbuffer = i.distance;
+            intersect = Intersect { object_id, ..i };
+        }
+    }
+
+    intersect
+}
+
+// Rejilla cúbica de bloques pequeños separados entre sí
+fn grid_of_cubes(count: usize) -> Vec<Cube> {
+    let material = Arc::new(Material::new(Color::new(120, 120, 120), 0.0, 0.6, Default::default(), None));
+
+    let side = (count as f32).cbrt().ceil() as usize;
+    let offset = side as f32 * 0.5;
+
+    (0..count)
+        .map(|i| {
+            let (x, y, z) = (i % side, (i / side) % side, i / (side * side));
+            Cube {
+                center: Vec3::new(x as f32 - offset, y as f32 - offset, z as f32 - offset),
+                dim_x: 0.35,
+                dim_y: 0.35,
+                dim_z: 0.35,
+                material: Arc::clone(&material),
+            }
+        })
+        .collect()
+}
+
+pub fn run(count: usize) {
+    let objects = grid_of_cubes(count);
+    let linear_objects = grid_of_cubes(count);
+
+    let build_start = Instant::now();
+    let bvh = Bvh::new(objects.into_iter().map(|cube| Box::new(cube) as Box<dyn RayIntersect>).collect());
+    let build_time = build_start.elapsed();
+
+    let side = (count as f32).cbrt().ceil();
+    let camera = Camera::new(
+        Vec3::new(side * 1.2, side * 0.8, side * 1.6),
+        Vec3::new(0.0, 0.0, 0.0),
+        Vec3::new(0.0, 1.0, 0.0),
+    );
+
+    let perspective_scale = (std::f32::consts::PI / 6.0).tan();
+    let aspect_ratio = BENCH_WIDTH as f32 / BENCH_HEIGHT as f32;
+    let rays: Vec<Ray> = (0..BENCH_WIDTH * BENCH_HEIGHT)
+        .map(|i| {
+            let (x, y) = (i % BENCH_WIDTH, i / BENCH_WIDTH);
+            let screen_x = ((2.0 * x as f32) / BENCH_WIDTH as f32 - 1.0) * aspect_ratio * perspective_scale;
+            let screen_y = (-(2.0 * y as f32) / BENCH_HEIGHT as f32 + 1.0) * perspective_scale;
+            let direction = camera.base_change(&normalize(&Vec3::new(screen_x, screen_y, -1.0)));
+            Ray::new(camera.eye, direction, RayKind::Primary, RayCone::new(0.0, 0.0))
+        })
+        .collect();
+
+    let linear_start = Instant::now();
+    let linear_hits: Vec<Intersect> = rays
+        .iter()
+        .map(|ray| linear_closest_hit(&linear_objects, ray))
+        .collect();
+    let linear_time = linear_start.elapsed();
+
+    let bvh_start = Instant::now();
+    let bvh_hits: Vec<Intersect> = rays
+        .iter()
+        .map(|ray| bvh.closest_hit(ray))
+        .collect();
+    let bvh_time = bvh_start.elapsed();
+
+    let mismatches = linear_hits
+        .iter()
+        .zip(&bvh_hits)
+        .filter(|(a, b)| {
+            a.is_intersecting != b.is_intersecting
+                || (a.is_intersecting && a.object_id != b.object_id)
+                || (a.distance - b.distance).abs() > 1e-4
+        })
+        .count();
+
+    println!("Cubos: {}, rayos: {}", count, rays.len());
+    println!("Construcción de la BVH: {:.2?}", build_time);
+    println!("Lineal: {:.2?}", linear_time);
+    println!("BVH:    {:.2?}", bvh_time);
+    println!("Aceleración: {:.1}x", linear_time.as_secs_f64() / bvh_time.as_secs_f64().max(1e-9));
+    println!("Diferencias entre ambos: {}", mismatches);
+}
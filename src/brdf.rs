@@ -0,0 +1,74 @@
+use nalgebra_glm::Vec3;
+use std::f32::consts::PI;
+
+use crate::color::Color;
+use crate::material::Material;
+
+// Con rugosidad 0 la distribución GGX es un delta y el brillo de una luz puntual desaparece
+const MIN_ALPHA: f32 = 0.002;
+
+// Reflectancia a incidencia normal de un dieléctrico con ese índice de refracción
+pub fn dielectric_f0(ior: f32) -> f32 {
+    let r = (ior - 1.0) / (ior + 1.0);
+    r * r
+}
+
+// Aproximación de Schlick; cosine es el coseno entre la vista y la normal (o el half vector)
+pub fn fresnel_schlick(f0: Color, cosine: f32) -> Color {
+    let weight = (1.0 - cosine).clamp(0.0, 1.0).powi(5);
+    Color::rgb(
+        f0.r + (1.0 - f0.r) * weight,
+        f0.g + (1.0 - f0.g) * weight,
+        f0.b + (1.0 - f0.b) * weight,
+    )
+}
+
+// Reflectancia a incidencia normal: la del dieléctrico, o el color base en los metales
+pub fn specular_f0(material: &Material, base_color: Color) -> Color {
+    let dielectric = dielectric_f0(material.ior);
+    let metallic = material.metallic;
+    Color::rgb(
+        dielectric * (1.0 - metallic) + base_color.r * metallic,
+        dielectric * (1.0 - metallic) + base_color.g * metallic,
+        dielectric * (1.0 - metallic) + base_color.b * metallic,
+    )
+}
+
+// Distribución de microfacetas GGX (Trowbridge–Reitz)
+fn ggx_distribution(n_dot_h: f32, alpha: f32) -> f32 {
+    let alpha2 = alpha * alpha;
+    let d = n_dot_h * n_dot_h * (alpha2 - 1.0) + 1.0;
+    alpha2 / (PI * d * d)
+}
+
+// Sombreado-enmascaramiento de Smith correlacionado, ya dividido por 4 (n·l)(n·v)
+fn smith_visibility(n_dot_l: f32, n_dot_v: f32, alpha: f32) -> f32 {
+    let alpha2 = alpha * alpha;
+    let view = n_dot_l * (n_dot_v * n_dot_v * (1.0 - alpha2) + alpha2).sqrt();
+    let light = n_dot_v * (n_dot_l * n_dot_l * (1.0 - alpha2) + alpha2).sqrt();
+    0.5 / (view + light).max(1e-8)
+}
+
+// BRDF de Cook-Torrance más un lóbulo difuso de Lambert, ya multiplicado por n·l.
+// view y light son unitarios y salen del punto. El difuso pierde lo que se lleva el
+// reflejo de Fresnel, lo que es metal y lo que se transmite.
+pub fn evaluate(material: &Material, base_color: Color, normal: &Vec3, view: &Vec3, light: &Vec3) -> Color {
+    let n_dot_l = normal.dot(light);
+    if n_dot_l <= 0.0 {
+        return Color::black();
+    }
+
+    let n_dot_v = normal.dot(view).max(1e-4);
+    let half = (light + view).normalize();
+    let n_dot_h = normal.dot(&half).max(0.0);
+    let v_dot_h = view.dot(&half).max(0.0);
+
+    let alpha = (material.roughness * material.roughness).max(MIN_ALPHA);
+    let fresnel = fresnel_schlick(specular_f0(material, base_color), v_dot_h);
+    let specular = fresnel * (ggx_distribution(n_dot_h, alpha) * smith_visibility(n_dot_l, n_dot_v, alpha));
+
+    let diffuse_weight = (1.0 - material.metallic) * (1.0 - material.transmission) / PI;
+    let diffuse = Color::rgb(1.0 - fresnel.r, 1.0 - fresnel.g, 1.0 - fresnel.b) * base_color * diffuse_weight;
+
+    (diffuse + specular) * n_dot_l
+}
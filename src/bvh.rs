@@ -0,0 +1,321 @@
+use nalgebra_glm::Vec3;
+
+use crate::aabb::Aabb;
+use crate::ray::Ray;
+use crate::ray_intersect::{Intersect, RayIntersect};
+
+const SAH_BINS: usize = 12;
+const MAX_LEAF_SIZE: usize = 2;
+const TRAVERSAL_COST: f32 = 1.0;
+const INTERSECTION_COST: f32 = 1.0;
+
+#[derive(Debug, Clone, Copy)]
+struct Node {
+    bounds: Aabb,
+    // En una hoja es el primer objeto; en un nodo interno, el hijo izquierdo (el derecho va después)
+    first: usize,
+    count: usize,  // 0 para nodos internos
+}
+
+impl Node {
+    fn is_leaf(&self) -> bool {
+        self.count > 0
+    }
+}
+
+// Jerarquía de volúmenes envolventes construida con SAH sobre las cajas de los objetos.
+// Guarda los objetos reordenados para que cada hoja apunte a un rango contiguo.
+pub struct Bvh {
+    objects: Vec<Box<dyn RayIntersect>>,
+    ids: Vec<usize>,  // Índice original de cada objeto reordenado
+    nodes: Vec<Node>,
+}
+
+#[derive(Clone, Copy)]
+struct Bin {
+    bounds: Aabb,
+    count: usize,
+}
+
+impl Bvh {
+    pub fn new(objects: Vec<Box<dyn RayIntersect>>) -> Self {
+        let boxes: Vec<Aabb> = objects.iter().map(|object| object.bounding_box()).collect();
+        let centroids: Vec<Vec3> = boxes.iter().map(|b| b.centroid()).collect();
+        let mut order: Vec<usize> = (0..objects.len()).collect();
+
+        let mut bvh = Bvh {
+            objects: Vec::new(),
+            ids: Vec::new(),
+            nodes: Vec::with_capacity(objects.len().max(1) * 2),
+        };
+
+        bvh.nodes.push(Node {
+            bounds: Aabb::empty(),
+            first: 0,
+            count: objects.len(),
+        });
+
+        if !objects.is_empty() {
+            bvh.subdivide(0, &boxes, &centroids, &mut order);
+        }
+
+        // Reordena los objetos según quedaron en las hojas
+        let mut slots: Vec<Option<Box<dyn RayIntersect>>> = objects.into_iter().map(Some).collect();
+        bvh.objects = order.iter().map(|&i| slots[i].take().unwrap()).collect();
+        bvh.ids = order;
+
+        bvh
+    }
+
+    fn subdivide(&mut self, node_index: usize, boxes: &[Aabb], centroids: &[Vec3], order: &mut [usize]) {
+        let first = self.nodes[node_index].first;
+        let count = self.nodes[node_index].count;
+        let range = &mut order[first..first + count];
+
+        let mut bounds = Aabb::empty();
+        let mut centroid_bounds = Aabb::empty();
+        for &i in range.iter() {
+            bounds = bounds.union(&boxes[i]);
+            centroid_bounds.grow(&centroids[i]);
+        }
+        self.nodes[node_index].bounds = bounds;
+
+        if count <= MAX_LEAF_SIZE {
+            return;
+        }
+
+        let Some((axis, split_bin, split_cost)) = find_split(range, boxes, centroids, &centroid_bounds) else {
+            return;
+        };
+
+        // Si partir no es más barato que probar todos los objetos, se queda como hoja
+        let leaf_cost = INTERSECTION_COST * count as f32;
+        if split_cost >= leaf_cost {
+            return;
+        }
+
+        let min = centroid_bounds.min[axis];
+        let scale = SAH_BINS as f32 / (centroid_bounds.max[axis] - min);
+        let mut left_count = 0;
+        for j in 0..range.len() {
+            if bin_index(centroids[range[j]][axis], min, scale) < split_bin {
+                range.swap(j, left_count);
+                left_count += 1;
+            }
+        }
+
+        if left_count == 0 || left_count == count {
+            return;
+        }
+
+        let left = self.nodes.len();
+        self.nodes.push(Node { bounds: Aabb::empty(), first, count: left_count });
+        self.nodes.push(Node { bounds: Aabb::empty(), first: first + left_count, count: count - left_count });
+        self.nodes[node_index].first = left;
+        self.nodes[node_index].count = 0;
+
+        self.subdivide(left, boxes, centroids, order);
+        self.subdivide(left + 1, boxes, centroids, order);
+    }
+
+    // Intersección más cercana dentro de max_distance
+    pub fn closest_hit(&self, ray: &Ray) -> Intersect<'_> {
+        let ray_origin = &ray.origin;
+        let ray_direction = &ray.direction;
+        let inv_direction = Vec3::new(1.0 / ray_direction.x, 1.0 / ray_direction.y, 1.0 / ray_direction.z);
+        let mut intersect = Intersect::empty();
+        let mut closest = ray.t_max;
+
+        if self.objects.is_empty() || self.nodes[0].bounds.hit(ray_origin, &inv_direction, closest).is_none() {
+            return intersect;
+        }
+
+        let mut stack = Vec::with_capacity(64);
+        stack.push(0);
+
+        while let Some(node_index) = stack.pop() {
+            let node = &self.nodes[node_index];
+
+            if node.is_leaf() {
+                for slot in node.first..node.first + node.count {
+                    let i = self.objects[slot].ray_intersect(ray);
+                    if i.is_intersecting && i.distance < closest {
+                        closest = i.distance;
+                        intersect = Intersect { object_id: self.ids[slot], ..i };
+                    }
+                }
+                continue;
+            }
+
+            // Visita primero el hijo más cercano para acortar closest cuanto antes
+            let left = node.first;
+            let right = node.first + 1;
+            let t_left = self.nodes[left].bounds.hit(ray_origin, &inv_direction, closest);
+            let t_right = self.nodes[right].bounds.hit(ray_origin, &inv_direction, closest);
+
+            match (t_left, t_right) {
+                (Some(tl), Some(tr)) => {
+                    if tl <= tr {
+                        stack.push(right);
+                        stack.push(left);
+                    } else {
+                        stack.push(left);
+                        stack.push(right);
+                    }
+                }
+                (Some(_), None) => stack.push(left),
+                (None, Some(_)) => stack.push(right),
+                (None, None) => {}
+            }
+        }
+
+        intersect
+    }
+}
+
+fn bin_index(centroid: f32, min: f32, scale: f32) -> usize {
+    (((centroid - min) * scale) as usize).min(SAH_BINS - 1)
+}
+
+// Busca el mejor corte por SAH con bins en los tres ejes: (eje, primer bin de la derecha, costo)
+fn find_split(range: &[usize], boxes: &[Aabb], centroids: &[Vec3], centroid_bounds: &Aabb) -> Option<(usize, usize, f32)> {
+    let mut best: Option<(usize, usize, f32)> = None;
+
+    let parent_area = range
+        .iter()
+        .fold(Aabb::empty(), |b, &i| b.union(&boxes[i]))
+        .surface_area();
+    if parent_area <= 0.0 {
+        return None;
+    }
+
+    for axis in 0..3 {
+        let min = centroid_bounds.min[axis];
+        let extent = centroid_bounds.max[axis] - min;
+        if extent <= 0.0 {
+            continue;
+        }
+        let scale = SAH_BINS as f32 / extent;
+
+        let mut bins = [Bin { bounds: Aabb::empty(), count: 0 }; SAH_BINS];
+        for &i in range {
+            let bin = &mut bins[bin_index(centroids[i][axis], min, scale)];
+            bin.bounds = bin.bounds.union(&boxes[i]);
+            bin.count += 1;
+        }
+
+        // Áreas y conteos acumulados desde la derecha
+        let mut right_area = [0.0; SAH_BINS];
+        let mut right_count = [0; SAH_BINS];
+        let mut accumulated = Aabb::empty();
+        let mut accumulated_count = 0;
+        for b in (1..SAH_BINS).rev() {
+            accumulated = accumulated.union(&bins[b].bounds);
+            accumulated_count += bins[b].count;
+            right_area[b] = accumulated.surface_area();
+            right_count[b] = accumulated_count;
+        }
+
+        let mut left_bounds = Aabb::empty();
+        let mut left_count = 0;
+        for split in 1..SAH_BINS {
+            left_bounds = left_bounds.union(&bins[split - 1].bounds);
+            left_count += bins[split - 1].count;
+            if left_count == 0 || right_count[split] == 0 {
+                continue;
+            }
+
+            let cost = TRAVERSAL_COST
+                + INTERSECTION_COST
+                    * (left_bounds.surface_area() * left_count as f32 + right_area[split] * right_count[split] as f32)
+                    / parent_area;
+
+            if best.is_none_or(|(_, _, best_cost)| cost < best_cost) {
+                best = Some((axis, split, cost));
+            }
+        }
+    }
+
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra_glm::Vec3;
+    use std::sync::Arc;
+
+    use super::Bvh;
+    use crate::cube::Cube;
+    use crate::material::Material;
+    use crate::ray::{Ray, RayCone, RayKind};
+    use crate::ray_intersect::{Intersect, RayIntersect};
+    use crate::sampler::Sampler;
+
+    // Rejilla de 10x10x10 celdas donde cada una tiene o no un cubo de tamaño y posición al
+    // azar, sin salirse de su celda para que dos cubos nunca empaten en distancia
+    fn random_cube_grid(sampler: &mut Sampler) -> Vec<Cube> {
+        let material = Arc::new(Material::black());
+        let mut cubes = Vec::new();
+
+        for i in 0..1000 {
+            if sampler.next_f32() > 0.4 {
+                continue;
+            }
+            let cell = Vec3::new((i % 10) as f32, ((i / 10) % 10) as f32, (i / 100) as f32) - Vec3::new(5.0, 5.0, 5.0);
+            let half: [f32; 3] = std::array::from_fn(|_| 0.1 + 0.3 * sampler.next_f32());
+            let jitter = Vec3::from_fn(|axis, _| (sampler.next_f32() - 0.5) * (1.0 - 2.0 * half[axis]));
+            cubes.push(Cube {
+                center: cell + Vec3::new(0.5, 0.5, 0.5) + jitter,
+                dim_x: half[0],
+                dim_y: half[1],
+                dim_z: half[2],
+                material: Arc::clone(&material),
+            });
+        }
+
+        cubes
+    }
+
+    fn linear_closest_hit<'a>(cubes: &'a [Cube], ray: &Ray) -> Intersect<'a> {
+        let mut closest = Intersect::empty();
+        for (object_id, cube) in cubes.iter().enumerate() {
+            let hit = cube.ray_intersect(ray);
+            if hit.is_intersecting && (!closest.is_intersecting || hit.distance < closest.distance) {
+                closest = Intersect { object_id, ..hit };
+            }
+        }
+        closest
+    }
+
+    #[test]
+    fn closest_hit_matches_linear_search() {
+        let cubes = random_cube_grid(&mut Sampler::new(3, 0));
+        let objects = random_cube_grid(&mut Sampler::new(3, 0))
+            .into_iter()
+            .map(|cube| Box::new(cube) as Box<dyn RayIntersect>)
+            .collect();
+        let bvh = Bvh::new(objects);
+
+        let mut sampler = Sampler::new(11, 0);
+        let mut hits = 0;
+        for _ in 0..5000 {
+            // Rayos desde afuera y desde adentro de la rejilla, en cualquier dirección
+            let origin = Vec3::from_fn(|_, _| (sampler.next_f32() - 0.5) * 30.0);
+            let target = Vec3::from_fn(|_, _| (sampler.next_f32() - 0.5) * 10.0);
+            let ray = Ray::new(origin, (target - origin).normalize(), RayKind::Shadow, RayCone::new(0.0, 0.0));
+
+            let expected = linear_closest_hit(&cubes, &ray);
+            let actual = bvh.closest_hit(&ray);
+
+            assert_eq!(actual.is_intersecting, expected.is_intersecting, "rayo desde {:?}", origin);
+            if expected.is_intersecting {
+                hits += 1;
+                assert_eq!(actual.object_id, expected.object_id);
+                assert_eq!(actual.face_index, expected.face_index);
+                assert!((actual.distance - expected.distance).abs() < 1e-4);
+            }
+        }
+
+        assert!(hits > 1000, "muy pocos rayos chocaron ({})", hits);
+    }
+}
@@ -2,6 +2,7 @@
 use nalgebra_glm::Vec3;
 use std::f32::consts::PI;
 
+#[derive(Clone, PartialEq)]
 pub struct Camera {
     pub eye: Vec3,
     pub center: Vec3,
@@ -24,7 +25,7 @@ impl Camera {
 
         let rotated = vector.x * right + vector.y * up - vector.z * forward;
 
-        return rotated.normalize();
+        rotated.normalize()
     }
 
     pub fn orbit(&mut self, delta_yaw: f32, delta_pitch: f32) {
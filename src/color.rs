@@ -1,34 +1,72 @@
 use std::fmt;
+use std::sync::OnceLock;
 
-#[derive(Debug, Clone, Copy)]
+// Radiancia en RGB lineal. No se satura: los valores mayores a 1 se conservan
+// hasta el tone mapping final.
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Color {
-    pub r: u8,
-    pub g: u8,
-    pub b: u8,
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+}
+
+// Tabla de sRGB de 8 bits a lineal, para no calcular powf en cada muestra de textura
+fn srgb_table() -> &'static [f32; 256] {
+    static TABLE: OnceLock<[f32; 256]> = OnceLock::new();
+    TABLE.get_or_init(|| std::array::from_fn(|i| srgb_to_linear(i as f32 / 255.0)))
+}
+
+pub fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+pub fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
 }
 
 impl Color {
-    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+    // Color en sRGB de 8 bits (como en las texturas y en los archivos de escena)
+    pub fn new(r: u8, g: u8, b: u8) -> Self {
+        let table = srgb_table();
+        Color {
+            r: table[r as usize],
+            g: table[g as usize],
+            b: table[b as usize],
+        }
+    }
+
+    // Color ya en espacio lineal
+    pub const fn rgb(r: f32, g: f32, b: f32) -> Self {
         Color { r, g, b }
     }
 
-    pub const fn from_hex(hex: u32) -> Self {
+    pub fn from_hex(hex: u32) -> Self {
         let r = ((hex >> 16) & 0xFF) as u8;
         let g = ((hex >> 8) & 0xFF) as u8;
         let b = (hex & 0xFF) as u8;
-        Color { r, g, b }
+        Color::new(r, g, b)
     }
 
     pub const fn black() -> Self {
-        Color { r: 0, g: 0, b: 0 }
+        Color { r: 0.0, g: 0.0, b: 0.0 }
     }
 
     pub const fn green() -> Self {
-        Color { r: 0, g: 255, b: 10 }
+        Color { r: 0.0, g: 1.0, b: 0.003 }
     }
 
-    pub fn to_hex(&self) -> u32 {
-        ((self.r as u32) << 16) | ((self.g as u32) << 8) | (self.b as u32)
+    // Codifica a sRGB de 8 bits; se espera un color ya pasado por el tone mapping
+    pub fn to_hex(self) -> u32 {
+        let encode = |c: f32| (linear_to_srgb(c.clamp(0.0, 1.0)) * 255.0).round() as u32;
+        (encode(self.r) << 16) | (encode(self.g) << 8) | encode(self.b)
     }
 }
 
@@ -39,9 +77,9 @@ impl Add for Color {
 
     fn add(self, other: Color) -> Color {
         Color {
-            r: self.r.saturating_add(other.r),
-            g: self.g.saturating_add(other.g),
-            b: self.b.saturating_add(other.b),
+            r: self.r + other.r,
+            g: self.g + other.g,
+            b: self.b + other.b,
         }
     }
 }
@@ -51,17 +89,30 @@ use std::ops::Mul;
 impl Mul<f32> for Color {
     type Output = Color;
 
+    // Los pesos negativos no aportan luz
     fn mul(self, scalar: f32) -> Color {
         Color {
-            r: (self.r as f32 * scalar).clamp(0.0, 255.0) as u8,
-            g: (self.g as f32 * scalar).clamp(0.0, 255.0) as u8,
-            b: (self.b as f32 * scalar).clamp(0.0, 255.0) as u8,
+            r: (self.r * scalar).max(0.0),
+            g: (self.g * scalar).max(0.0),
+            b: (self.b * scalar).max(0.0),
+        }
+    }
+}
+
+impl Mul<Color> for Color {
+    type Output = Color;
+
+    fn mul(self, other: Color) -> Color {
+        Color {
+            r: self.r * other.r,
+            g: self.g * other.g,
+            b: self.b * other.b,
         }
     }
 }
 
 impl fmt::Display for Color {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Color(r: {}, g: {}, b: {})", self.r, self.g, self.b)
+        write!(f, "Color(r: {:.3}, g: {:.3}, b: {:.3})", self.r, self.g, self.b)
     }
 }
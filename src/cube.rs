@@ -1,20 +1,23 @@
-use nalgebra_glm::Vec3;
+use std::sync::Arc;
+use nalgebra_glm::{Vec2, Vec3};
+use crate::aabb::Aabb;
+use crate::ray::Ray;
 use crate::ray_intersect::{RayIntersect, Intersect};
 use crate::material::Material;
-use crate::Color;
-use crate::texture::Texture;
-use std::rc::Rc;
 
 pub struct Cube {
     pub center: Vec3,
     pub dim_x: f32,  // Mitad de la dimensión en x
     pub dim_y: f32,  // Mitad de la dimensión en y
     pub dim_z: f32,  // Mitad de la dimensión en z
-    pub material: Rc<Material>,
+    pub material: Arc<Material>,
 }
 
 impl RayIntersect for Cube {
-    fn ray_intersect(&self, ray_origin: &Vec3, ray_direction: &Vec3, camera_position: &Vec3) -> Intersect {
+    fn ray_intersect(&self, ray: &Ray) -> Intersect<'_> {
+        let ray_origin = &ray.origin;
+        let ray_direction = &ray.direction;
+
         // Calcular las esquinas mínimas y máximas del cubo
         let min = self.center - Vec3::new(self.dim_x, self.dim_y, self.dim_z);
         let max = self.center + Vec3::new(self.dim_x, self.dim_y, self.dim_z);
@@ -65,104 +68,73 @@ impl RayIntersect for Cube {
             t_max = tz_max;
         }
 
-        // Si t_min es negativo, la intersección está detrás del origen del rayo
-        if t_min < 0.0 {
+        // Si t_max es negativo, el cubo está detrás del origen del rayo
+        if t_max < ray.t_min {
+            return Intersect::empty();
+        }
+
+        // El rayo nace dentro del cubo: solo puede chocar con la cara por la que sale
+        if t_min < ray.t_min {
+            return self.exit_face_hit(ray, t_max, &min, &max, false);
+        }
+
+        // El cubo empieza después del final del rayo (p. ej. detrás de la luz de una sombra)
+        if t_min > ray.t_max {
             return Intersect::empty();
         }
 
        // Calcular el punto de intersección y la normal de la cara
        let intersection_point = ray_origin + ray_direction * t_min;
-       let ( mut normal, face_index) = self.calculate_normal_and_face_index(&intersection_point, &min, &max);
+       let (normal, face_index) = self.calculate_normal_and_face_index(&intersection_point, &min, &max);
 
-       // Verificar si la cara es visible desde la cámara
-       if !self.is_face_visible(&normal, camera_position, &intersection_point) {
+       // Cara trasera respecto del rayo (solo la descartan los rayos que la piden)
+       if ray.is_back_face(&normal) {
            return Intersect::empty();
        }
-       let (u, v) = self.get_texture_coordinates(&intersection_point, &min, &max);
+       let (u, v) = face_texture_coordinates(face_index, &intersection_point, &min, &max);
        // Si la cara es visible, continuar con el cálculo
        let distance = t_min;
 
-       let texture_color = match &self.material.textures[face_index] {
-        Some(texture) => {
-            let u_clamped = u.clamp(0.0, 1.0 - f32::EPSILON);
-            let v_clamped = v.clamp(0.0, 1.0 - f32::EPSILON);
-    
-            let tex_x = (u_clamped * texture.width as f32) as u32;
-            let tex_y = (v_clamped * texture.height as f32) as u32;
-    
-            let pixel = texture.get_pixel(tex_x, tex_y);
-            Color::new(pixel[0], pixel[1], pixel[2])
-        }
-        None => self.material.diffuse,
-    };
-    
-
-    // Ajustar la normal con el normal map si está disponible
-    if let Some(normal_map) = &self.material.normal_map {
-        let u_clamped = u.clamp(0.0, 1.0 - f32::EPSILON);
-        let v_clamped = v.clamp(0.0, 1.0 - f32::EPSILON);
-
-        let tex_x = (u_clamped * normal_map.width as f32) as u32;
-        let tex_y = (v_clamped * normal_map.height as f32) as u32;
-
-        let pixel = normal_map.get_pixel(tex_x, tex_y);
-
-        let normal_tangent = Vec3::new(
-            (pixel[0] as f32 / 255.0) * 2.0 - 1.0,
-            (pixel[1] as f32 / 255.0) * 2.0 - 1.0,
-            (pixel[2] as f32 / 255.0) * 2.0 - 1.0,
-        )
-        .normalize();
+       let footprint = ray.cone.uv_footprint(distance, ray_direction, &normal, face_world_size(face_index, &min, &max));
+       let hit = Intersect::surface(&self.material, intersection_point, normal, distance, face_index, Vec2::new(u, v), footprint);
+       if let Some(hit) = hit {
+           let (tangent, bitangent) = face_uv_axes(face_index);
+           return hit.with_tangents(tangent, bitangent);
+       }
 
-        let tangent = normal.cross(&Vec3::new(0.0, 1.0, 0.0)).normalize();
-        let bitangent = normal.cross(&tangent);
+       // El texel de entrada es transparente: se ve el interior de la cara de salida
+       self.exit_face_hit(ray, t_max, &min, &max, true)
+   }
 
-        normal = (tangent * normal_tangent.x
-            + bitangent * normal_tangent.y
-            + normal * normal_tangent.z)
-            .normalize();
+    fn bounding_box(&self) -> Aabb {
+        let half = Vec3::new(self.dim_x, self.dim_y, self.dim_z);
+        Aabb::new(self.center - half, self.center + half)
     }
-
-    let albedo = [
-        self.material.albedo[0],
-        self.material.albedo[1],
-        self.material.albedo[2],
-        *self.material.albedo.get(3).unwrap_or(&0.0),
-    ];
-    
-
-    let textures = self.material.textures.clone();
-
-
-    let material = Rc::new(Material::new(
-        texture_color,
-        self.material.specular,
-        albedo,
-        self.material.refractive_index,
-        textures,
-        self.material.normal_map.clone(),
-    ));
-
-    Intersect::new(
-        intersection_point,
-        normal,
-        distance,
-        material
-    )
-
-   }
 }
 
 impl Cube {
+    // Impacto en la cara por la que el rayo sale del cubo. inward da vuelta la normal para
+    // sombrear su lado interior (visto a través de un texel transparente); si no, queda
+    // hacia afuera, como la necesita la refracción al salir de un sólido.
+    fn exit_face_hit(&self, ray: &Ray, t_max: f32, min: &Vec3, max: &Vec3, inward: bool) -> Intersect<'_> {
+        if t_max > ray.t_max {
+            return Intersect::empty();
+        }
 
-    pub fn is_face_visible(&self, face_normal: &Vec3, camera_position: &Vec3, intersection_point: &Vec3) -> bool {
-        // Dirección desde el punto de intersección hacia la cámara
-        let view_direction = (camera_position - intersection_point).normalize();
-        // Producto punto entre la normal de la cara y la dirección de la cámara
-        let dot_product = face_normal.dot(&view_direction);
+        let exit_point = ray.at(t_max);
+        let (exit_normal, exit_face) = self.calculate_normal_and_face_index(&exit_point, min, max);
+        let normal = if inward { -exit_normal } else { exit_normal };
+        if ray.is_back_face(&normal) {
+            return Intersect::empty();
+        }
 
-        // Si el producto punto es positivo, la cara está orientada hacia la cámara
-        dot_product > 0.0
+        let (u, v) = face_texture_coordinates(exit_face, &exit_point, min, max);
+        let footprint = ray.cone.uv_footprint(t_max, &ray.direction, &exit_normal, face_world_size(exit_face, min, max));
+
+        let (tangent, bitangent) = face_uv_axes(exit_face);
+        Intersect::surface(&self.material, exit_point, normal, t_max, exit_face, Vec2::new(u, v), footprint)
+            .map(|hit| hit.with_tangents(tangent, bitangent))
+            .unwrap_or_else(Intersect::empty)
     }
 
     fn calculate_normal_and_face_index(&self, point: &Vec3, min: &Vec3, max: &Vec3) -> (Vec3, usize) {
@@ -182,35 +154,94 @@ impl Cube {
             (Vec3::new(0.0, 0.0, 1.0), 5)
         }
     }
-    
+}
 
-    fn get_texture_coordinates(&self, point: &Vec3, min: &Vec3, max: &Vec3) -> (f32, f32) {
-        let epsilon = 1e-4;
+// Tamaño en el mundo de una unidad de UV sobre la cara (media geométrica de sus dos lados)
+pub fn face_world_size(face_index: usize, min: &Vec3, max: &Vec3) -> f32 {
+    let size = max - min;
+    match face_index / 2 {
+        0 => (size.z * size.y).sqrt(),
+        1 => (size.x * size.z).sqrt(),
+        _ => (size.x * size.y).sqrt(),
+    }
+}
 
-        if (point.x - min.x).abs() < epsilon {
+// Direcciones en las que crecen u y v sobre cada cara; deben seguir a face_texture_coordinates
+pub fn face_uv_axes(face_index: usize) -> (Vec3, Vec3) {
+    match face_index {
+        0 => (Vec3::z(), Vec3::y()),
+        1 => (Vec3::z(), -Vec3::y()),
+        2 => (Vec3::x(), Vec3::z()),
+        3 => (Vec3::x(), -Vec3::z()),
+        4 => (Vec3::x(), Vec3::y()),
+        _ => (Vec3::x(), -Vec3::y()),
+    }
+}
+
+// Coordenadas UV de un punto sobre la cara indicada de una caja (orden de caras: -x, +x, -y, +y, -z, +z)
+pub fn face_texture_coordinates(face_index: usize, point: &Vec3, min: &Vec3, max: &Vec3) -> (f32, f32) {
+    match face_index {
+        0 => {
             let u = (point.z - min.z) / (max.z - min.z);
             let v = (point.y - min.y) / (max.y - min.y);
             (u, v)
-        } else if (point.x - max.x).abs() < epsilon {
+        }
+        1 => {
             let u = (point.z - min.z) / (max.z - min.z);
             let v = (max.y - point.y) / (max.y - min.y);
             (u, v)
-        } else if (point.y - min.y).abs() < epsilon {
+        }
+        2 => {
             let u = (point.x - min.x) / (max.x - min.x);
             let v = (point.z - min.z) / (max.z - min.z);
             (u, v)
-        } else if (point.y - max.y).abs() < epsilon {
+        }
+        3 => {
             let u = (point.x - min.x) / (max.x - min.x);
             let v = (max.z - point.z) / (max.z - min.z);
             (u, v)
-        } else if (point.z - min.z).abs() < epsilon {
+        }
+        4 => {
             let u = (point.x - min.x) / (max.x - min.x);
             let v = (point.y - min.y) / (max.y - min.y);
             (u, v)
-        } else {
+        }
+        _ => {
             let u = (point.x - min.x) / (max.x - min.x);
             let v = (max.y - point.y) / (max.y - min.y);
             (u, v)
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use nalgebra_glm::Vec3;
+    use std::sync::Arc;
+
+    use super::Cube;
+    use crate::material::Material;
+    use crate::ray::{Ray, RayCone, RayKind};
+    use crate::ray_intersect::RayIntersect;
+
+    #[test]
+    fn ignores_hits_past_t_max() {
+        let cube = Cube {
+            center: Vec3::zeros(),
+            dim_x: 1.0,
+            dim_y: 1.0,
+            dim_z: 1.0,
+            material: Arc::new(Material::black()),
+        };
+        let shadow = Ray::new(Vec3::new(0.0, 0.0, -5.0), Vec3::z(), RayKind::Shadow, RayCone::new(0.0, 0.0));
+
+        // La luz queda antes del cubo
+        assert!(!cube.ray_intersect(&shadow.with_max_distance(3.0)).is_intersecting);
+        assert!(cube.ray_intersect(&shadow.with_max_distance(10.0)).is_intersecting);
+
+        // Desde adentro, la luz queda antes de la cara de salida
+        let inside = Ray { origin: Vec3::zeros(), ..shadow };
+        assert!(!cube.ray_intersect(&inside.with_max_distance(0.5)).is_intersecting);
+        assert!(cube.ray_intersect(&inside.with_max_distance(1.5)).is_intersecting);
+    }
+}
@@ -0,0 +1,61 @@
+use nalgebra_glm::Vec3;
+use std::f32::consts::PI;
+
+use crate::color::Color;
+use crate::texture::{Texture, TextureFilter, WrapMode};
+
+// Imagen que rodea la escena: una sola en proyección equirectangular, o seis caras de un
+// cubo en el orden +x, -x, +y, -y, +z, -z
+pub enum EnvironmentImage {
+    Equirectangular(Texture),
+    Cubemap(Box<[Texture; 6]>),
+}
+
+pub struct EnvironmentMap {
+    pub image: EnvironmentImage,
+    pub rotation: f32,  // Giro alrededor del eje y, en radianes
+    pub intensity: f32,
+}
+
+impl EnvironmentMap {
+    // Color en esa dirección (unitaria, saliendo de la escena)
+    pub fn radiance(&self, direction: &Vec3) -> Color {
+        // Girar el entorno es girar la dirección al revés
+        let (sin, cos) = self.rotation.sin_cos();
+        let d = Vec3::new(cos * direction.x - sin * direction.z, direction.y, sin * direction.x + cos * direction.z);
+
+        let texel = match &self.image {
+            EnvironmentImage::Equirectangular(texture) => {
+                // El centro de la imagen mira hacia -z y la fila de arriba es el cenit
+                let u = 0.5 + d.x.atan2(-d.z) / (2.0 * PI);
+                let v = d.y.clamp(-1.0, 1.0).acos() / PI;
+                texture.sample(u, v, 0.0, TextureFilter::Bilinear, WrapMode::Repeat)
+            }
+            EnvironmentImage::Cubemap(faces) => {
+                let (face, u, v) = cube_face(&d);
+                faces[face].sample(u, v, 0.0, TextureFilter::Bilinear, WrapMode::Clamp)
+            }
+        };
+
+        Color::rgb(texel[0], texel[1], texel[2]) * self.intensity
+    }
+}
+
+// Cara del cubo que atraviesa la dirección y (u, v) dentro de ella. Cada cara es lo que se
+// ve desde el centro mirando por su eje, sin espejar: las laterales con +y arriba, la de
+// arriba con +z arriba y la de abajo con -z arriba, como al levantar o bajar la vista
+// mirando hacia -z
+fn cube_face(d: &Vec3) -> (usize, f32, f32) {
+    let (ax, ay, az) = (d.x.abs(), d.y.abs(), d.z.abs());
+    let (face, major, s, t) = if ax >= ay && ax >= az {
+        if d.x > 0.0 { (0, ax, d.z, -d.y) } else { (1, ax, -d.z, -d.y) }
+    } else if ay >= az {
+        if d.y > 0.0 { (2, ay, d.x, -d.z) } else { (3, ay, d.x, d.z) }
+    } else if d.z > 0.0 {
+        (4, az, -d.x, -d.y)
+    } else {
+        (5, az, d.x, -d.y)
+    };
+
+    (face, 0.5 * (s / major + 1.0), 0.5 * (t / major + 1.0))
+}
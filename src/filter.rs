@@ -0,0 +1,56 @@
+use std::str::FromStr;
+
+// Filtro de reconstrucción para promediar las muestras de un píxel
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Filter {
+    Box,
+    Tent,
+    Mitchell,  // Mitchell–Netravali con B = C = 1/3
+}
+
+impl FromStr for Filter {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, String> {
+        match name {
+            "box" => Ok(Filter::Box),
+            "tent" => Ok(Filter::Tent),
+            "mitchell" => Ok(Filter::Mitchell),
+            _ => Err(format!("Filtro desconocido '{}' (se espera box, tent o mitchell)", name)),
+        }
+    }
+}
+
+fn mitchell_1d(x: f32) -> f32 {
+    const B: f32 = 1.0 / 3.0;
+    const C: f32 = 1.0 / 3.0;
+    let x = x.abs();
+
+    if x < 1.0 {
+        ((12.0 - 9.0 * B - 6.0 * C) * x * x * x + (-18.0 + 12.0 * B + 6.0 * C) * x * x + (6.0 - 2.0 * B)) / 6.0
+    } else if x < 2.0 {
+        ((-B - 6.0 * C) * x * x * x + (6.0 * B + 30.0 * C) * x * x + (-12.0 * B - 48.0 * C) * x + (8.0 * B + 24.0 * C)) / 6.0
+    } else {
+        0.0
+    }
+}
+
+impl Filter {
+    // Radio del filtro en píxeles, medido desde el centro del píxel
+    pub fn radius(self) -> f32 {
+        match self {
+            Filter::Box => 0.5,
+            Filter::Tent => 1.0,
+            Filter::Mitchell => 2.0,
+        }
+    }
+
+    // Peso de una muestra desplazada (dx, dy) píxeles del centro. Mitchell puede dar pesos negativos
+    pub fn weight(self, dx: f32, dy: f32) -> f32 {
+        match self {
+            Filter::Box => 1.0,
+            Filter::Tent => (1.0 - dx.abs()).max(0.0) * (1.0 - dy.abs()).max(0.0),
+            Filter::Mitchell => mitchell_1d(dx) * mitchell_1d(dy),
+        }
+    }
+}
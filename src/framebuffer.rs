@@ -1,3 +1,5 @@
+use image::{Rgb, RgbImage};
+
 
 pub struct Framebuffer {
     pub width: usize,
@@ -37,4 +39,17 @@ impl Framebuffer {
     pub fn set_current_color(&mut self, color: u32) {
         self.current_color = color;
     }
+
+    // Guarda el contenido del buffer como imagen (el formato sale de la extensión)
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let mut image = RgbImage::new(self.width as u32, self.height as u32);
+
+        for (pixel, hex) in image.pixels_mut().zip(self.buffer.iter()) {
+            *pixel = Rgb([(hex >> 16) as u8, (hex >> 8) as u8, *hex as u8]);
+        }
+
+        image
+            .save(path)
+            .map_err(|e| format!("Error al guardar la imagen: {}", e))
+    }
 }
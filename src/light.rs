@@ -1,30 +1,203 @@
 
 use nalgebra_glm::Vec3;
 use crate::color::Color;
+use std::f32::consts::PI;
 use std::fmt;
 
+// Forma de la luz. Las direcciones son unitarias; en las de área indican hacia dónde emiten
+#[derive(Clone, PartialEq)]
+pub enum LightKind {
+    Point { position: Vec3, radius: f32 },  // Con radio es una esfera; 0 es un punto
+    Directional {
+        direction: Vec3,  // Hacia donde viaja la luz; está en el infinito
+        angle: f32,       // Radio aparente del disco, en radianes; 0 da sombras duras
+    },
+    Spot {
+        position: Vec3,
+        direction: Vec3,
+        angle: f32,   // Medio ángulo del cono, en radianes
+        blend: f32,   // Fracción del cono en la que el borde se difumina
+        radius: f32,
+    },
+    Rect {
+        position: Vec3,   // Centro
+        direction: Vec3,
+        half_u: Vec3,     // Medio lado en cada eje del rectángulo
+        half_v: Vec3,
+    },
+    Disk { position: Vec3, direction: Vec3, radius: f32 },
+}
+
+// Cómo se atenúa la luz con la distancia. La intensidad es la que llega a una unidad de
+// distancia (o en toda la escena sin atenuación)
+#[derive(Clone, Copy, PartialEq)]
+pub enum Falloff {
+    None,
+    InverseSquare,
+    Linear,
+    Range { range: f32 },  // Inverso del cuadrado que se apaga suavemente hasta 0 en range
+}
 
+// Por debajo de esta distancia la atenuación deja de crecer, para no dividir por casi 0
+const MIN_FALLOFF_DISTANCE: f32 = 0.01;
+
+impl Falloff {
+    pub fn attenuation(&self, distance: f32) -> f32 {
+        let distance = distance.max(MIN_FALLOFF_DISTANCE);
+        match self {
+            Falloff::None => 1.0,
+            Falloff::InverseSquare => 1.0 / (distance * distance),
+            Falloff::Linear => 1.0 / distance,
+            Falloff::Range { range } => {
+                let window = (1.0 - (distance / range).powi(4)).clamp(0.0, 1.0);
+                window * window / (distance * distance)
+            }
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
 pub struct Light {
-    pub position: Vec3,
+    pub kind: LightKind,
     pub color: Color,
     pub intensity: f32,
+    pub falloff: Falloff,  // Las direccionales no la usan
+}
+
+// Lo que llega de una luz a un punto: hacia dónde está, a qué distancia (infinita para las
+// direccionales) y con qué intensidad, ya con el cono del spot o el ángulo del área pero
+// sin la atenuación por distancia
+pub struct LightSample {
+    pub direction: Vec3,
+    pub distance: f32,
+    pub intensity: f32,
+}
+
+// Dos ejes perpendiculares a normal
+pub fn orthonormal_basis(normal: &Vec3) -> (Vec3, Vec3) {
+    let helper = if normal.x.abs() < 0.9 { Vec3::x() } else { Vec3::y() };
+    let u = helper.cross(normal).normalize();
+    (u, normal.cross(&u))
+}
+
+// Lleva el cuadrado unitario al disco unitario sin deformar mucho las celdas (Shirley–Chiu);
+// el centro del cuadrado cae en el centro del disco
+fn concentric_disk(u: f32, v: f32) -> (f32, f32) {
+    let a = 2.0 * u - 1.0;
+    let b = 2.0 * v - 1.0;
+    if a == 0.0 && b == 0.0 {
+        return (0.0, 0.0);
+    }
+
+    let (radius, angle) = if a.abs() > b.abs() {
+        (a, PI / 4.0 * (b / a))
+    } else {
+        (b, PI / 2.0 - PI / 4.0 * (a / b))
+    };
+    (radius * angle.cos(), radius * angle.sin())
 }
 
 impl Light {
-    pub fn new(position: Vec3, color: Color, intensity: f32) -> Self {
+    pub fn new(kind: LightKind, color: Color, intensity: f32) -> Self {
         Light {
-            position,
+            kind,
             color,
             intensity,
+            falloff: Falloff::InverseSquare,
+        }
+    }
+
+    // Atenuación a esa distancia; las direccionales están en el infinito y no se atenúan
+    pub fn attenuation(&self, distance: f32) -> f32 {
+        match self.kind {
+            LightKind::Directional { .. } => 1.0,
+            _ => self.falloff.attenuation(distance),
+        }
+    }
+
+    // Si la luz ocupa espacio; las que no dan sombras duras y basta una muestra
+    pub fn has_extent(&self) -> bool {
+        match &self.kind {
+            LightKind::Point { radius, .. } | LightKind::Spot { radius, .. } => *radius > 0.0,
+            LightKind::Directional { angle, .. } => *angle > 0.0,
+            LightKind::Rect { .. } | LightKind::Disk { .. } => true,
+        }
+    }
+
+    // Muestra la luz vista desde point. (u, v) en [0, 1) elige el punto de la luz; (0.5, 0.5)
+    // es su centro. None si la luz no alcanza el punto.
+    pub fn sample(&self, point: &Vec3, u: f32, v: f32) -> Option<LightSample> {
+        // Las esferas se ven desde el punto como un disco perpendicular a la línea que las une
+        let towards = |center: Vec3, radius: f32, intensity: f32| {
+            let mut target = center;
+            if radius > 0.0 {
+                let (axis_u, axis_v) = orthonormal_basis(&(center - point).normalize());
+                let (x, y) = concentric_disk(u, v);
+                target += (axis_u * x + axis_v * y) * radius;
+            }
+            let offset = target - point;
+            let distance = offset.magnitude();
+            Some(LightSample { direction: offset / distance, distance, intensity })
+        };
+
+        match &self.kind {
+            LightKind::Point { position, radius } => towards(*position, *radius, self.intensity),
+            LightKind::Directional { direction, angle } => {
+                let mut to_light = -direction;
+                if *angle > 0.0 {
+                    let (axis_u, axis_v) = orthonormal_basis(&to_light);
+                    let (x, y) = concentric_disk(u, v);
+                    to_light = (to_light + (axis_u * x + axis_v * y) * angle.tan()).normalize();
+                }
+                Some(LightSample {
+                    direction: to_light,
+                    distance: f32::INFINITY,
+                    intensity: self.intensity,
+                })
+            }
+            LightKind::Spot { position, direction, angle, blend, radius } => {
+                let cosine = direction.dot(&(point - position).normalize());
+                let outer = angle.cos();
+                let inner = (angle * (1.0 - blend)).cos();
+                if cosine <= outer {
+                    return None;
+                }
+                // Borde suave entre el cono interior y el exterior
+                let t = ((cosine - outer) / (inner - outer).max(1e-6)).clamp(0.0, 1.0);
+                towards(*position, *radius, self.intensity * t * t * (3.0 - 2.0 * t))
+            }
+            LightKind::Rect { position, direction, half_u, half_v } => {
+                let target = position + half_u * (2.0 * u - 1.0) + half_v * (2.0 * v - 1.0);
+                area_sample(point, target, direction, self.intensity)
+            }
+            LightKind::Disk { position, direction, radius } => {
+                let (x, y) = concentric_disk(u, v);
+                let (axis_u, axis_v) = orthonormal_basis(direction);
+                let target = position + (axis_u * x + axis_v * y) * *radius;
+                area_sample(point, target, direction, self.intensity)
+            }
+        }
+    }
+
+    // Punto de referencia para describir la luz (y decidir si es de día): su posición, o
+    // para las direccionales un punto lejano en el sentido opuesto al que viaja la luz
+    pub fn reference_position(&self) -> Vec3 {
+        match &self.kind {
+            LightKind::Directional { direction, .. } => -direction * 40.0,
+            LightKind::Point { position, .. }
+            | LightKind::Spot { position, .. }
+            | LightKind::Rect { position, .. }
+            | LightKind::Disk { position, .. } => *position,
         }
     }
 
     pub fn light_condition(&mut self){
-        let y_position = self.position.y;
-        let x_position = self.position.x;
-        if y_position >= 20.0 && y_position < 40.0 {
+        let reference = self.reference_position();
+        let y_position = reference.y;
+        let x_position = reference.x;
+        if (20.0..40.0).contains(&y_position) {
             println!("Dia");
-        } else if y_position >= 0.0 && y_position < 20.0 {
+        } else if (0.0..20.0).contains(&y_position) {
             if x_position >0.0{
                 println!("Amanecer");
             }else{
@@ -35,28 +208,50 @@ impl Light {
         }
     }
 
+    // Ciclo de x e y para simular el ciclo de día y noche. Las luces con posición la mueven
+    // por la órbita; las direccionales apuntan desde ese punto hacia el centro, con la misma
+    // inclinación en z que se les configuró.
     pub fn update_position_orbit(&mut self, center: Vec3, radius: f32, angle: f32) {
-        let fixed_z = self.position.z;
-    
-        // Ciclo de x e y para hacer simular el ciclo  de día y noche
-        self.position.x = center.x + radius * angle.cos();
-        self.position.y = center.y + radius * angle.sin();
-        
-        self.position.z = fixed_z;
+        match &mut self.kind {
+            LightKind::Directional { direction, .. } => {
+                let tilt = direction.z;
+                let planar = (1.0 - tilt * tilt).sqrt();
+                *direction = Vec3::new(-angle.cos() * planar, -angle.sin() * planar, tilt);
+            }
+            LightKind::Point { position, .. }
+            | LightKind::Spot { position, .. }
+            | LightKind::Rect { position, .. }
+            | LightKind::Disk { position, .. } => {
+                position.x = center.x + radius * angle.cos();
+                position.y = center.y + radius * angle.sin();
+            }
+        }
+    }
+}
+
+// Las luces de área emiten solo por su cara frontal, con la intensidad proyectada según
+// el ángulo con que se las ve
+fn area_sample(point: &Vec3, target: Vec3, direction: &Vec3, intensity: f32) -> Option<LightSample> {
+    let offset = target - point;
+    let distance = offset.magnitude();
+    let to_point = -offset / distance;
+    let cosine = direction.dot(&to_point);
+    if cosine <= 0.0 {
+        return None;
     }
-    
 
-    
+    Some(LightSample { direction: offset / distance, distance, intensity: intensity * cosine })
 }
 
 
 
 impl fmt::Display for Light {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let position = self.reference_position();
         write!(
             f,
             "Light(Position: ({}, {}, {}))",
-            self.position.x, self.position.y, self.position.z,
+            position.x, position.y, position.z,
         )
     }
 }
@@ -1,34 +1,67 @@
 
 mod framebuffer;
+mod ray;
 mod ray_intersect;
 mod cube;
+mod sphere;
+mod mesh;
+mod obj_loader;
+mod transform;
+mod tonemap;
+mod accumulation;
+mod sampler;
+mod sky;
+mod environment;
+mod filter;
+mod aabb;
+mod bvh;
+mod voxel_grid;
+mod bench;
 mod color;
 mod camera;
 mod light;
 mod material;
+mod brdf;
 mod texture;
+mod assets;
+mod options;
+mod scene;
+mod scene_file;
 
-use minifb::{ Window, WindowOptions, Key };
+use minifb::{ Window, WindowOptions, Key, KeyRepeat };
 use nalgebra_glm::{Vec3, normalize};
 use std::time::Duration;
 use std::f32::consts::PI;
-use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
 
+use crate::brdf::fresnel_schlick;
 use crate::color::Color;
-use crate::ray_intersect::{Intersect, RayIntersect};
-use crate::cube::Cube;
+use crate::ray::{Ray, RayCone, RayKind};
+use crate::ray_intersect::Intersect;
+use crate::scene::Scene;
+use crate::tonemap::tone_map;
+use crate::sampler::Sampler;
+use crate::accumulation::{Accumulator, SampleSum};
 use crate::framebuffer::Framebuffer;
 use crate::camera::Camera;
-use crate::light::Light;
-use crate::material::Material;
-use crate::texture::Texture;
+use crate::light::{Light, LightSample};
+use crate::options::{Options, USAGE};
+use crate::scene_file::{load_scene, LoadedScene, RenderSettings};
 
 const ORIGIN_BIAS: f32 = 1e-4;
-const SKYBOX_COLOR: Color = Color::new(68, 142, 228);
+// Rugosidad desde la que ya no se trazan reflejos nítidos
+const MAX_MIRROR_ROUGHNESS: f32 = 0.5;
 
+// Por debajo de este peso el rayo reflejado no cambia la imagen y no vale su costo
+const MIN_REFLECTION_WEIGHT: f32 = 1e-3;
+
+// Aleja el origen del rayo siguiente de la superficie por el lado hacia el que sale. Se usa
+// la normal geométrica: la interpolada de una malla puede no coincidir con la cara real
 fn offset_origin(intersect: &Intersect, direction: &Vec3) -> Vec3 {
-    let offset = intersect.normal * ORIGIN_BIAS;
-    if direction.dot(&intersect.normal) < 0.0 {
+    let offset = intersect.geometric_normal * ORIGIN_BIAS;
+    if direction.dot(&intersect.geometric_normal) < 0.0 {
         intersect.point - offset
     } else {
         intersect.point + offset
@@ -40,17 +73,18 @@ fn reflect(incident: &Vec3, normal: &Vec3) -> Vec3 {
 }
 
 fn refract(incident: &Vec3, normal: &Vec3, eta_t: f32) -> Vec3 {
-    let cosi = -incident.dot(normal).max(-1.0).min(1.0);
+    let cosi = -incident.dot(normal).clamp(-1.0, 1.0);
     
     let (n_cosi, eta, n_normal);
 
+    // eta es n1 / n2: al salir del material (el rayo va en el sentido de la normal) se invierte
     if cosi < 0.0 {
         n_cosi = -cosi;
-        eta = 1.0 / eta_t;
+        eta = eta_t;
         n_normal = -normal;
     } else {
         n_cosi = cosi;
-        eta = eta_t;
+        eta = 1.0 / eta_t;
         n_normal = *normal;
     }
     
@@ -65,146 +99,337 @@ fn refract(incident: &Vec3, normal: &Vec3, eta_t: f32) -> Vec3 {
 
 fn fresnel(incident: &Vec3, normal: &Vec3, ior: f32) -> f32 {
     let mut cosi = incident.dot(normal).clamp(-1.0, 1.0);
-    let etai = 1.0;
-    let etat = ior;
+    let (mut etai, mut etat) = (1.0, ior);
+    // El rayo sale del material: puede haber reflexión total interna
+    if cosi > 0.0 {
+        std::mem::swap(&mut etai, &mut etat);
+    }
     let sint = etai / etat * (1.0 - cosi * cosi).sqrt();
 
     if sint >= 1.0 {
-        return 1.0;
+        1.0
     } else {
         let cost = (1.0 - sint * sint).sqrt();
         cosi = cosi.abs();
         let rs = ((etat * cosi) - (etai * cost)) / ((etat * cosi) + (etai * cost));
         let rp = ((etai * cosi) - (etat * cost)) / ((etai * cosi) + (etat * cost));
-        return (rs * rs + rp * rp) / 2.0;
+        (rs * rs + rp * rp) / 2.0
     }
 }
 
-fn cast_shadow(
+// Luz que llega desde el punto muestreado, por canal: negro si algo opaco la tapa. Para las
+// direccionales el rayo no tiene fin. El rayo atraviesa las superficies semitransparentes y
+// las que transmiten, que dejan pasar lo que no reflejan teñido de su color; no se desvía,
+// así que no hay cáusticas.
+fn shadow_transmittance(
     intersect: &Intersect,
-    light: &Light,
-    objects: &[Cube],
-    camera: &Camera
-) -> f32 {
-    let light_dir = (light.position - intersect.point).normalize();
-    let light_distance = (light.position - intersect.point).magnitude();
-
-    let shadow_ray_origin = offset_origin(intersect, &light_dir);
-    let mut shadow_intensity = 0.0;
-
-    for object in objects {
-        let shadow_intersect = object.ray_intersect(&shadow_ray_origin, &light_dir, &camera.eye);
-        if shadow_intersect.is_intersecting && shadow_intersect.distance < light_distance {
-            let distance_ratio = shadow_intersect.distance / light_distance;
-            shadow_intensity = 1.0 - distance_ratio.powf(2.0).min(1.0);
+    sample: &LightSample,
+    scene: &Scene,
+    cone: &RayCone,
+) -> Color {
+    let light_dir = sample.direction;
+    let mut shadow_ray = Ray::new(offset_origin(intersect, &light_dir), light_dir, RayKind::Shadow, *cone);
+    let mut travelled = 0.0;
+    let mut transmittance = Color::rgb(1.0, 1.0, 1.0);
+
+    loop {
+        let remaining = sample.distance - travelled;
+        let shadow_intersect = scene.closest_hit(&shadow_ray.with_max_distance(remaining));
+        if !shadow_intersect.is_intersecting {
             break;
         }
+
+        travelled += shadow_intersect.distance;
+        let material = shadow_intersect.material;
+        let opacity = shadow_intersect.opacity;
+        let transmitted = (1.0 - material.metallic) * material.transmission;
+        if opacity >= 1.0 && transmitted <= 0.0 {
+            return Color::black();
+        }
+
+        let mut passing = Color::rgb(1.0, 1.0, 1.0) * (1.0 - opacity);
+        if transmitted > 0.0 {
+            // Fresnel como si el rayo entrara: al no desviarlo, la cara de salida daría una
+            // reflexión total interna que con la refracción real no ocurre
+            let (tint, _) = shadow_intersect.shade();
+            let normal = shadow_intersect.normal;
+            let facing = if light_dir.dot(&normal) > 0.0 { -normal } else { normal };
+            let kt = 1.0 - fresnel(&light_dir, &facing, material.ior);
+            passing = passing + tint * (opacity * transmitted * kt);
+        }
+        transmittance = transmittance * passing;
+
+        if transmittance.r.max(transmittance.g).max(transmittance.b) <= 1e-3 {
+            return Color::black();
+        }
+        shadow_ray.origin = offset_origin(&shadow_intersect, &light_dir);
     }
 
-    shadow_intensity
+    transmittance
 }
 
-pub fn cast_ray(
-    ray_origin: &Vec3,
-    ray_direction: &Vec3,
-    objects: &[Cube],
-    lights: &[Light],
-    depth: u32,
-    camera: &Camera,
-) -> Color {
+pub fn cast_ray(ray: &Ray, scene: &Scene, depth: u32, settings: &RenderSettings, sampler: &mut Sampler) -> Color {
     if depth > 3 {
-        return SKYBOX_COLOR;
+        return scene.background(&ray.direction);
     }
 
-    let mut intersect = Intersect::empty();
-    let mut zbuffer = f32::INFINITY;
+    let intersect = scene.closest_hit(ray);
+    let ray_direction = &ray.direction;
 
-    for object in objects {
-        let i = object.ray_intersect(ray_origin, ray_direction,&camera.eye);
-        if i.is_intersecting && i.distance < zbuffer {
-            zbuffer = i.distance;
-            intersect = i;
+    if !intersect.is_intersecting {
+        return scene.background(&ray.direction);
+    }
+
+    // Los rayos que salen del punto heredan el cono, ya abierto hasta esta distancia
+    let secondary_cone = ray.cone.advance(intersect.distance);
+
+    // Texel semitransparente: se mezcla con lo que sigue detrás en la misma dirección
+    let opacity = intersect.opacity;
+    let behind_color = if opacity < 1.0 {
+        let behind_origin = offset_origin(&intersect, ray_direction);
+        cast_ray(&Ray::new(behind_origin, *ray_direction, ray.kind, secondary_cone), scene, depth, settings, sampler)
+    } else {
+        Color::black()
+    };
+
+    // El texel y el mapa de normales solo se evalúan para el impacto que se sombrea
+    let (base_color, normal) = intersect.shade();
+    let material = intersect.material;
+    let view_dir = -ray_direction.normalize();
+    let mut final_color = material.emission;
+
+    // Las luces con tamaño se muestrean en varios puntos repartidos sobre su superficie y se
+    // promedia lo que llega de cada uno; así la penumbra sale de la visibilidad parcial
+    for light in &scene.lights {
+        let count = if light.has_extent() { settings.shadow_samples.max(1) } else { 1 };
+        let mut received = Color::black();
+
+        for i in 0..count {
+            let (u, v) = if count > 1 { sampler.stratified_2d(i, count) } else { (0.5, 0.5) };
+            let Some(sample) = light.sample(&intersect.point, u, v) else {
+                continue;
+            };
+            let intensity = sample.intensity * light.attenuation(sample.distance);
+            if intensity <= 0.0 {
+                continue;
+            }
+
+            // Sin luz directa no hace falta el rayo de sombra
+            let reflected = brdf::evaluate(material, base_color, &normal, &view_dir, &sample.direction);
+            if reflected == Color::black() {
+                continue;
+            }
+
+            let transmittance = shadow_transmittance(&intersect, &sample, scene, &secondary_cone);
+            received = received + reflected * transmittance * intensity;
         }
+
+        // La intensidad está escalada para que una superficie blanca y mate, de frente a la
+        // luz, devuelva exactamente esa intensidad
+        final_color = final_color + received * light.color * (PI / count as f32);
     }
 
-    if !intersect.is_intersecting {
-        return SKYBOX_COLOR;
+    // Reflejo y transmisión trazados. Los brillos de las luces ya están en el BRDF; aquí
+    // se agrega lo que se ve en la dirección especular.
+    let kr = fresnel(ray_direction, &normal, material.ior);
+    let cos_view = normal.dot(&view_dir).abs();
+    let metal_reflectance = fresnel_schlick(base_color, cos_view);
+    let reflectance = Color::rgb(
+        kr * (1.0 - material.metallic) + metal_reflectance.r * material.metallic,
+        kr * (1.0 - material.metallic) + metal_reflectance.g * material.metallic,
+        kr * (1.0 - material.metallic) + metal_reflectance.b * material.metallic,
+    );
+
+    // Sin muestrear el lóbulo glossy, el reflejo nítido se apaga a medida que crece la
+    // rugosidad. La parte transmitida se traza nítida, así que su reflejo también: si no,
+    // la reflexión total interna perdería energía.
+    let fade = (1.0 - material.roughness / MAX_MIRROR_ROUGHNESS).clamp(0.0, 1.0).powi(2);
+    let transmitted = (1.0 - material.metallic) * material.transmission;
+    let gloss = transmitted + (1.0 - transmitted) * fade;
+    let reflect_weight = reflectance * gloss;
+    if reflect_weight.r.max(reflect_weight.g).max(reflect_weight.b) > MIN_REFLECTION_WEIGHT {
+        let reflect_dir = reflect(ray_direction, &normal).normalize();
+        let reflect_origin = offset_origin(&intersect, &reflect_dir);
+        let reflect_ray = Ray::new(reflect_origin, reflect_dir, RayKind::Reflection, secondary_cone);
+        let reflect_color = cast_ray(&reflect_ray, scene, depth + 1, settings, sampler);
+        final_color = final_color + reflect_color * reflect_weight;
     }
 
-    let mut final_color = Color::black();
+    let transparency = (1.0 - kr) * (1.0 - material.metallic) * material.transmission;
+    if transparency > 0.0 {
+        let refract_dir = refract(ray_direction, &normal, material.ior);
+        let refract_origin = offset_origin(&intersect, &refract_dir);
+        let refract_ray = Ray::new(refract_origin, refract_dir, RayKind::Refraction, secondary_cone);
+        let refract_color = cast_ray(&refract_ray, scene, depth + 1, settings, sampler);
+        final_color = final_color + refract_color * base_color * transparency;
+    }
 
-    for light in lights {
-        let light_dir = (light.position - intersect.point).normalize();
-        let view_dir = (ray_origin - intersect.point).normalize();
-        let reflect_dir = reflect(&-light_dir, &intersect.normal).normalize();
+    final_color * opacity + behind_color * (1.0 - opacity)
+}
 
-        let shadow_intensity = cast_shadow(&intersect, light, objects, camera);
-        let light_intensity = light.intensity * (1.0 - shadow_intensity);
+const TILE_SIZE: usize = 32;
 
-        let diffuse_intensity = intersect.normal.dot(&light_dir).max(0.0).min(1.0);
-        let diffuse = intersect.material.diffuse * intersect.material.albedo[0] * diffuse_intensity * light_intensity;
+// Pasadas tras las que la vista quieta se considera convergida y se deja de trazar
+const MAX_ACCUMULATED_PASSES: u64 = 256;
 
-        let specular_intensity = view_dir.dot(&reflect_dir).max(0.0).powf(intersect.material.specular);
-        let specular = light.color * intersect.material.albedo[1] * specular_intensity * light_intensity;
-        let kr = fresnel(ray_direction, &intersect.normal, intersect.material.refractive_index,);
+// Traza una pasada de la imagen y devuelve la suma ponderada de muestras de cada píxel
+// (fila por fila). La pasada 0 con una sola muestra lanza el rayo por la esquina del píxel,
+// como el render original; las demás reparten muestras estratificadas dentro del radio del
+// filtro alrededor del centro, con semillas que dependen del píxel y de la pasada.
+pub fn trace_pass(
+    width: usize,
+    height: usize,
+    scene: &Scene,
+    camera: &Camera,
+    settings: &RenderSettings,
+    pass: u64,
+) -> Vec<SampleSum> {
+    let aspect_ratio = width as f32 / height as f32;
+    let perspective_scale = (settings.fov * 0.5).tan();
 
-        let mut reflect_color = Color::green();
-        let reflectivity = kr * intersect.material.albedo[2];
-        if reflectivity > 0.0 {
-            let reflect_dir = reflect(&ray_direction, &intersect.normal).normalize();
-            let reflect_origin = offset_origin(&intersect, &reflect_dir);
-            reflect_color = cast_ray(&reflect_origin, &reflect_dir, objects, lights, depth + 1, camera);
-        }
+    // Los rayos primarios parten del ojo y se abren el ángulo que cubre un píxel
+    let cone = RayCone::new(0.0, 2.0 * perspective_scale / height as f32);
+
+    // Rayo que pasa por el punto (px, py) de la pantalla, en píxeles
+    let primary_ray = |px: f32, py: f32| -> Ray {
+        let screen_x = (2.0 * px) / width as f32 - 1.0;
+        let screen_y = -(2.0 * py) / height as f32 + 1.0;
+
+        let screen_x = screen_x * aspect_ratio * perspective_scale;
+        let screen_y = screen_y * perspective_scale;
+
+        let ray_direction = normalize(&Vec3::new(screen_x, screen_y, -1.0));
+
+        Ray::new(camera.eye, camera.base_change(&ray_direction), RayKind::Primary, cone)
+    };
+
+    let samples = settings.samples.max(1);
+    let filter = settings.filter;
 
-        let mut refract_color = Color::green();
-        let transparency = (1.0 - kr) * intersect.material.albedo[3];
-        if transparency > 0.0 {
-            let refract_dir = refract(&ray_direction, &intersect.normal, intersect.material.refractive_index);
-            let refract_origin = offset_origin(&intersect, &refract_dir);
-            refract_color = cast_ray(&refract_origin, &refract_dir, objects, lights, depth + 1, camera);
+    let trace_pixel = |x: usize, y: usize| -> SampleSum {
+        let mut sum = SampleSum::default();
+        let mut sampler = Sampler::for_pixel(x, y, pass);
+
+        if pass == 0 && samples == 1 {
+            let color = cast_ray(&primary_ray(x as f32, y as f32), scene, 0, settings, &mut sampler);
+            sum.add(color, filter.weight(-0.5, -0.5));
+            return sum;
         }
 
-        final_color = final_color + (diffuse + specular) * (1.0 - reflectivity - transparency)
-            + (reflect_color * reflectivity)
-            + (refract_color * transparency);
-    }
+        let radius = filter.radius();
 
-    final_color
-}
+        for i in 0..samples {
+            let (u, v) = sampler.stratified_2d(i, samples);
+            let dx = (2.0 * u - 1.0) * radius;
+            let dy = (2.0 * v - 1.0) * radius;
 
-pub fn render(framebuffer: &mut Framebuffer, objects: &[Cube], camera: &Camera, lights: &[Light]) {
-    let width = framebuffer.width as f32;
-    let height = framebuffer.height as f32;
-    let aspect_ratio = width / height;
-    let fov = PI / 3.0;
-    let perspective_scale = (fov * 0.5).tan();
+            let ray = primary_ray(x as f32 + 0.5 + dx, y as f32 + 0.5 + dy);
+            sum.add(cast_ray(&ray, scene, 0, settings, &mut sampler), filter.weight(dx, dy));
+        }
 
-    for y in 0..framebuffer.height {
-        for x in 0..framebuffer.width {
-            let screen_x = (2.0 * x as f32) / width - 1.0;
-            let screen_y = -(2.0 * y as f32) / height + 1.0;
+        sum
+    };
 
-            let screen_x = screen_x * aspect_ratio * perspective_scale;
-            let screen_y = screen_y * perspective_scale;
+    let threads = match settings.threads {
+        0 => thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+        n => n,
+    };
 
-            let ray_direction = normalize(&Vec3::new(screen_x, screen_y, -1.0));
+    let mut sums = vec![SampleSum::default(); width * height];
 
-            let rotated_direction = camera.base_change(&ray_direction);
+    if threads <= 1 {
+        for y in 0..height {
+            for x in 0..width {
+                sums[y * width + x] = trace_pixel(x, y);
+            }
+        }
+        return sums;
+    }
 
-            let pixel_color = cast_ray(&camera.eye, &rotated_direction, objects, lights, 0, camera);
+    let tiles_x = width.div_ceil(TILE_SIZE);
+    let tiles_y = height.div_ceil(TILE_SIZE);
+    let tile_count = tiles_x * tiles_y;
+    let next_tile = AtomicUsize::new(0);
+
+    thread::scope(|scope| {
+        let (sender, receiver) = mpsc::channel();
+
+        for _ in 0..threads.min(tile_count) {
+            let sender = sender.clone();
+            let next_tile = &next_tile;
+            let trace_pixel = &trace_pixel;
+
+            scope.spawn(move || loop {
+                let tile = next_tile.fetch_add(1, Ordering::Relaxed);
+                if tile >= tile_count {
+                    break;
+                }
+
+                let x0 = (tile % tiles_x) * TILE_SIZE;
+                let y0 = (tile / tiles_x) * TILE_SIZE;
+                let x1 = (x0 + TILE_SIZE).min(width);
+                let y1 = (y0 + TILE_SIZE).min(height);
+
+                let mut pixels = Vec::with_capacity((x1 - x0) * (y1 - y0));
+                for y in y0..y1 {
+                    for x in x0..x1 {
+                        pixels.push(trace_pixel(x, y));
+                    }
+                }
+
+                if sender.send((x0, y0, x1, pixels)).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(sender);
 
-            framebuffer.set_current_color(pixel_color.to_hex());
-            framebuffer.point(x, y);
+        for (x0, y0, x1, pixels) in receiver {
+            let tile_width = x1 - x0;
+            for (i, sum) in pixels.into_iter().enumerate() {
+                sums[(y0 + i / tile_width) * width + x0 + i % tile_width] = sum;
+            }
         }
+    });
+
+    sums
+}
+
+// Render de un solo cuadro, sin acumulación
+pub fn render(
+    framebuffer: &mut Framebuffer,
+    scene: &Scene,
+    camera: &Camera,
+    settings: &RenderSettings,
+) {
+    let sums = trace_pass(framebuffer.width, framebuffer.height, scene, camera, settings, 0);
+
+    for (i, sum) in sums.iter().enumerate() {
+        let color = tone_map(sum.resolve(), settings.exposure, settings.tone_mapping);
+        framebuffer.set_current_color(color.to_hex());
+        framebuffer.point(i % framebuffer.width, i / framebuffer.width);
     }
 }
 
+fn render_headless(options: &Options, mut scene: LoadedScene) -> Result<(), String> {
+    let width = options.width.unwrap_or(scene.settings.width);
+    let height = options.height.unwrap_or(scene.settings.height);
+    let mut framebuffer = Framebuffer::new(width, height);
 
-fn main() {
-    let window_width = 800;
-    let window_height = 600;
-    let framebuffer_width = 800;
-    let framebuffer_height = 600;
+    options.apply(&mut scene.settings);
+
+    let light_angle = options.light_angle.or(scene.settings.light_angle);
+    scene.update_lights(light_angle.unwrap_or(90.0).to_radians());
+
+    render(&mut framebuffer, &scene.scene, &scene.camera, &scene.settings);
+    framebuffer.save(&options.output)
+}
+
+fn run_window(options: &Options, mut scene: LoadedScene) {
+    let window_width = options.width.unwrap_or(scene.settings.width);
+    let window_height = options.height.unwrap_or(scene.settings.height);
+    let framebuffer_width = window_width;
+    let framebuffer_height = window_height;
     let frame_delay = Duration::from_millis(16);
 
     let mut framebuffer = Framebuffer::new(framebuffer_width, framebuffer_height);
@@ -216,232 +441,84 @@ fn main() {
         WindowOptions::default(),
     ).unwrap();
 
-    
-    let grass_textures: [Option<Texture>; 6] = [
-        Texture::from_file("src/textures/grass_top.png").ok(),
-        Texture::from_file("src/textures/grass_top.png").ok(),
-        Texture::from_file("src/textures/grass_top.png").ok(),
-        Texture::from_file("src/textures/grass_top.png").ok(),
-        Texture::from_file("src/textures/grass_top.png").ok(),
-        Texture::from_file("src/textures/grass_top.png").ok(),
-    ];
-
-    let water_textures: [Option<Texture>; 6] = [
-        Texture::from_file("src/textures/water.png").ok(),
-        Texture::from_file("src/textures/water.png").ok(),
-        Texture::from_file("src/textures/water.png").ok(),
-        Texture::from_file("src/textures/water.png").ok(),
-        Texture::from_file("src/textures/water.png").ok(),
-        Texture::from_file("src/textures/water.png").ok(),
-    ];
-
-    let wood_textures: [Option<Texture>; 6] = [
-        Texture::from_file("src/textures/wood.png").ok(),
-        Texture::from_file("src/textures/wood.png").ok(),
-        Texture::from_file("src/textures/wood.png").ok(),
-        Texture::from_file("src/textures/wood.png").ok(),
-        Texture::from_file("src/textures/wood.png").ok(),
-        Texture::from_file("src/textures/wood.png").ok(),
-    ];
-
-    let furnace_textures: [Option<Texture>; 6] = [
-        Texture::from_file("src/textures/furnace_side.png").ok(),
-        Texture::from_file("src/textures/furnace_front.png").ok(),
-        Texture::from_file("src/textures/furnace_side.png").ok(),
-        Texture::from_file("src/textures/furnace_top.png").ok(),
-        Texture::from_file("src/textures/furnace_side.png").ok(),
-        Texture::from_file("src/textures/wood.png").ok(),
-    ];
-
-    let craft_textures: [Option<Texture>; 6] = [
-        Texture::from_file("src/textures/crafting_table_side2.png").ok(),
-        Texture::from_file("src/textures/crafting_table_side2.png").ok(),
-        Texture::from_file("src/textures/furnace_side.png").ok(),
-        Texture::from_file("src/textures/crafting_table_top.png").ok(),
-        Texture::from_file("src/textures/crafting_table_side1.png").ok(),
-        Texture::from_file("src/textures/wood.png").ok(),
-    ];
-
-    let leaf_textures: [Option<Texture>; 6] = [
-        Texture::from_file("src/textures/leaf.png").ok(),
-        Texture::from_file("src/textures/leaf.png").ok(),
-        Texture::from_file("src/textures/leaf.png").ok(),
-        Texture::from_file("src/textures/leaf.png").ok(),
-        Texture::from_file("src/textures/leaf.png").ok(),
-        Texture::from_file("src/textures/leaf.png").ok(),
-    ];
-
-    let grass = Rc::new(
-        Material::new(        
-            Color::new(96, 160, 54),
-            50.0,
-            [1.0, 0.0, 0.0, 0.0],
-            0.0,
-            grass_textures,
-            None,
-    ));
-
-    let water = Rc::new(
-        Material::new(
-            Color::new(10, 40, 225),
-            50.0,
-            [1.0, 0.1, 0.0, 0.0],
-            0.0,
-            water_textures,
-            None,
-        )
-    );
-    
-    let wood = Rc::new(
-        Material::new(    
-        Color::new(10, 40, 225),
-        50.0,
-        [0.6, 0.3, 0.0, 0.0],        
-        0.0,
-        wood_textures,
-        None,
-    ));
-
-    let furnace = Rc::new(
-        Material::new(        
-        Color::new(10, 40, 225),
-        50.0,
-        [0.6, 0.3, 0.0, 0.0],        
-        0.0,
-        furnace_textures,
-        None,
-    ));
-
-    let crafting_table = Rc::new(
-        Material::new(        
-        Color::new(10, 40, 225),
-        50.0,
-        [0.6, 0.3, 0.0, 0.0],        
-        0.0,
-        craft_textures,
-        None,
-    ));
-
-    let leaf = Rc::new(
-        Material::new(        
-        Color::new(10, 40, 225),
-        50.0,
-        [0.8, 0.2, 0.0, 0.0],        
-        0.0,
-        leaf_textures,
-        None,
-    ));
-    
-
-    let cube_size = 2.75;
-
-    let objects = [
-        //River 2*3
-        Cube {center: Vec3::new(0.0 , -0.6, cube_size * -8.0), dim_x: cube_size * 2.0, dim_y: cube_size - 0.6, dim_z: cube_size * 3.0, material: Rc::clone(&water),},
-        //Lake 7*6
-        Cube {center: Vec3::new(cube_size * 1.0 , -0.6, cube_size * 1.0), dim_x: cube_size * 7.0, dim_y: cube_size - 0.6, dim_z: cube_size * 6.0, material: Rc::clone(&water),},
-
-
-        // Floor 4*3
-        Cube {center: Vec3::new(cube_size * -6.0, 0.0, -cube_size * 8.0), dim_x: cube_size * 4.0, dim_y: cube_size, dim_z: cube_size * 3.0, material: Rc::clone(&grass),},
-        //Floor 2*6
-        Cube {center: Vec3::new(cube_size * -8.0, 0.0, -cube_size * -1.0), dim_x: cube_size * 2.0, dim_y: cube_size, dim_z: cube_size * 6.0, material:  Rc::clone(&grass),},
-        //Floor 10*1
-        Cube {center: Vec3::new(cube_size * 0.0, 0.0, -cube_size * -8.0), dim_x: cube_size * 10.0, dim_y: cube_size, dim_z: cube_size * 1.0, material:  Rc::clone(&grass),},
-        //Floor 1*6
-        Cube {center: Vec3::new(cube_size * 9.0, 0.0, -cube_size * -1.0), dim_x: cube_size * 1.0, dim_y: cube_size, dim_z: cube_size * 6.0, material:  Rc::clone(&grass),},
-        //Floor 4*3
-        Cube {center: Vec3::new(cube_size * 6.0, 0.0, -cube_size * 8.0), dim_x: cube_size * 4.0, dim_y: cube_size, dim_z: cube_size * 3.0, material:  Rc::clone(&grass),},
-        
-
-        //Objetos
-        //Mesa de Crafteo 1*1
-        Cube {center: Vec3::new(cube_size *  -9.0, cube_size * 2.0, -cube_size * -6.0), dim_x: cube_size, dim_y: cube_size, dim_z: cube_size, material:  Rc::clone(&crafting_table),},
-        //Horno 1*1
-        Cube {center: Vec3::new(cube_size *  -9.0, cube_size * 2.0, -cube_size * -4.0), dim_x: cube_size, dim_y: cube_size, dim_z: cube_size, material:  Rc::clone(&furnace),},
-        //Tronco 1*1*4
-        Cube {center: Vec3::new(cube_size *  -7.0, cube_size * 4.0, -cube_size * 8.0), dim_x: cube_size, dim_y: cube_size * 4.0, dim_z: cube_size, material:  Rc::clone(&wood),},
-        //Hojas 3*1*3
-        Cube {center: Vec3::new(cube_size *  -7.0, cube_size * 9.0, -cube_size * 8.0), dim_x: cube_size * 3.0, dim_y: cube_size, dim_z: cube_size * 3.0, material:  Rc::clone(&leaf),},
-        Cube {center: Vec3::new(cube_size *  -7.0, cube_size * 11.0, -cube_size * 8.0), dim_x: cube_size, dim_y: cube_size, dim_z: cube_size, material:  Rc::clone(&leaf),},
-    ];
-
-    
+    options.apply(&mut scene.settings);
 
-    let mut camera = Camera::new(
-        Vec3::new(0.0, 0.0, 100.0),
-        Vec3::new(0.0, 0.0, 0.0),
-        Vec3::new(0.0, 1.0, 0.0),
-    );
+    let light_angle = options.light_angle.or(scene.settings.light_angle);
+    let mut angle_sun = light_angle.unwrap_or(0.0).to_radians();
+    // Espacio pausa o reanuda la órbita, para dejar que la imagen se acumule
+    let mut orbit_lights = light_angle.is_none();
 
-    let sun = Light::new(
-        Vec3::new(0.0, 40.0, 0.0),
-        Color::new(255, 255, 224),
-        2.0,
-    );
-    
-    let moon = Light::new(
-        Vec3::new(0.0, -40.0, 0.0),
-        Color::new(173, 216, 230),
-        0.5,
-    );
-    // Centro de la órbita
-    let center = Vec3::new(0.0, 0.0, 0.0);
-    let radius = 40.0;
-    let mut angle_sun = 0.0;
-    let mut angle_moon = std::f32::consts::PI;
-    
     // Simulación de tiempo
     let delta_time = 0.2;
-    let mut lights: [Light; 2] = [sun, moon];
     let rotation_speed = PI/10.0;
 
 
+    let mut accumulator = Accumulator::new(framebuffer_width, framebuffer_height);
+    let mut last_camera: Option<Camera> = None;
+    let mut last_lights: Vec<Light> = Vec::new();
+
     while window.is_open() && !window.is_key_down(Key::Escape) {
 
-        // Actualiza las posiciones del Sol y la Luna
-        lights[0].update_position_orbit(center, radius, angle_sun); // Actualiza el Sol
-        lights[1].update_position_orbit(center, radius, angle_moon); // Actualiza la Luna
-        
-        // Incrementa los ángulos para orbitar
-        angle_sun += delta_time;
-        angle_moon += delta_time;
-        
-        // Para que los ángulos no desborden
-        if angle_sun > 2.0 * std::f32::consts::PI {
-            angle_sun -= 2.0 * std::f32::consts::PI;
-        }
-        if angle_moon > 2.0 * std::f32::consts::PI {
-            angle_moon -= 2.0 * std::f32::consts::PI;
+        if window.is_key_pressed(Key::Space, KeyRepeat::No) {
+            orbit_lights = !orbit_lights;
         }
 
-        lights[0].light_condition();
+        // Actualiza las posiciones del Sol y la Luna
+        scene.update_lights(angle_sun);
 
+        // Incrementa el ángulo para orbitar, salvo que esté en pausa
+        if orbit_lights {
+            angle_sun += delta_time;
+        }
 
+        // Para que el ángulo no desborde
+        if angle_sun > 2.0 * PI {
+            angle_sun -= 2.0 * PI;
+        }
 
-        render(&mut framebuffer, &objects, &camera, &lights);
+        if let Some(sun) = scene.scene.lights.first_mut() {
+            sun.light_condition();
+        }
 
         if window.is_key_down(Key::Left) || window.is_key_down(Key::A) {
-            camera.orbit(rotation_speed, 0.0); 
+            scene.camera.orbit(rotation_speed, 0.0); 
         }
 
         if window.is_key_down(Key::Right)||
         window.is_key_down(Key::D) {
-            camera.orbit(-rotation_speed, 0.0);
+            scene.camera.orbit(-rotation_speed, 0.0);
         }
 
         if window.is_key_down(Key::Up)||
         window.is_key_down(Key::W) {
-            camera.orbit(0.0, -rotation_speed);
+            scene.camera.orbit(0.0, -rotation_speed);
         }
 
         if window.is_key_down(Key::Down)||
         window.is_key_down(Key::S) {
-            camera.orbit(0.0, rotation_speed);
+            scene.camera.orbit(0.0, rotation_speed);
         }
 
+        // Cualquier cambio en la vista descarta lo acumulado
+        if last_camera.as_ref() != Some(&scene.camera) || last_lights != scene.scene.lights {
+            accumulator.reset();
+            last_camera = Some(scene.camera.clone());
+            last_lights = scene.scene.lights.clone();
+        }
 
-        render(&mut framebuffer, &objects, &camera, &lights);
+        // Con la vista quieta se siguen sumando muestras hasta MAX_ACCUMULATED_PASSES
+        if accumulator.passes() < MAX_ACCUMULATED_PASSES {
+            let pass = trace_pass(
+                framebuffer_width,
+                framebuffer_height,
+                &scene.scene,
+                &scene.camera,
+                &scene.settings,
+                accumulator.passes(),
+            );
+            accumulator.add_pass(&pass);
+            accumulator.resolve_into(&mut framebuffer, &scene.settings);
+        }
 
         window
             .update_with_buffer(&framebuffer.buffer, framebuffer_width, framebuffer_height)
@@ -449,4 +526,66 @@ fn main() {
 
         std::thread::sleep(frame_delay);
     }
-}   
+}
+
+fn main() {
+    let options = match Options::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            if !message.is_empty() {
+                eprintln!("{}\n", message);
+            }
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    if let Some(count) = options.bench_bvh {
+        bench::run(count);
+        return;
+    }
+
+    let scene = match load_scene(&options.scene) {
+        Ok(scene) => scene,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    if options.headless {
+        if let Err(e) = render_headless(&options, scene) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        println!("Imagen guardada en {}", options.output);
+    } else {
+        run_window(&options, scene);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render, Framebuffer};
+    use crate::scene_file::load_scene;
+
+    // Cada píxel usa su propia secuencia aleatoria, así que repartir los tiles entre hilos
+    // no puede cambiar ni un bit de la imagen
+    #[test]
+    fn threads_do_not_change_the_image() {
+        let mut scene = load_scene("scenes/voxels.toml").unwrap();
+        scene.update_lights(60.0_f32.to_radians());
+        scene.settings.samples = 2;
+        scene.settings.shadow_samples = 2;
+
+        let mut frames = Vec::new();
+        for threads in [1, 4] {
+            scene.settings.threads = threads;
+            let mut framebuffer = Framebuffer::new(64, 48);
+            render(&mut framebuffer, &scene.scene, &scene.camera, &scene.settings);
+            frames.push(framebuffer.buffer);
+        }
+
+        assert!(frames[0] == frames[1], "la imagen con 4 hilos difiere de la de un hilo");
+    }
+}
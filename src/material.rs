@@ -1,44 +1,234 @@
+use nalgebra_glm::Vec3;
+use std::str::FromStr;
 use crate::color::Color;
-use crate::texture::Texture;
+use crate::ray_intersect::Intersect;
+use crate::texture::{Texture, TextureFilter, WrapMode};
 
+// Cómo se usa el canal alfa de las texturas
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AlphaMode {
+    Opaque,  // Se ignora el alfa
+    Cutout,  // Los texels con alfa menor que alpha_cutoff no existen
+    Blend,   // El alfa mezcla la superficie con lo que hay detrás
+}
+
+impl FromStr for AlphaMode {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, String> {
+        match name {
+            "opaque" => Ok(AlphaMode::Opaque),
+            "cutout" => Ok(AlphaMode::Cutout),
+            "blend" => Ok(AlphaMode::Blend),
+            _ => Err(format!("Modo de alfa desconocido '{}' (se espera opaque, cutout o blend)", name)),
+        }
+    }
+}
+
+// Hacia dónde apunta el canal verde del normal map
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NormalMapConvention {
+    OpenGl,   // Verde hacia arriba en la imagen (Blender, glTF)
+    DirectX,  // Verde hacia abajo (Unreal, 3ds Max)
+}
+
+impl FromStr for NormalMapConvention {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, String> {
+        match name {
+            "opengl" => Ok(NormalMapConvention::OpenGl),
+            "directx" => Ok(NormalMapConvention::DirectX),
+            _ => Err(format!("Convención de normal map desconocida '{}' (se espera opengl o directx)", name)),
+        }
+    }
+}
+
+// Índice de refracción de un dieléctrico común (vidrio, plástico) cuando no se indica otro
+pub const DEFAULT_IOR: f32 = 1.5;
+
+// Material de metalicidad y rugosidad, evaluado con GGX / Cook-Torrance
 #[derive(Debug, Clone)]
 pub struct Material {
-  pub diffuse: Color,
-  pub specular: f32,
-  pub albedo: Vec<f32>,  // Este es un vector
-  pub refractive_index: f32,
+  pub base_color: Color,    // Multiplica a la textura de la cara, si hay
+  pub metallic: f32,        // 0 dieléctrico, 1 metal: el color base pasa a teñir el reflejo
+  pub roughness: f32,       // 0 espejo, 1 completamente mate
+  pub ior: f32,             // Índice de refracción; fija la reflectancia de los dieléctricos
+  pub transmission: f32,    // Fracción de la luz no reflejada que atraviesa la superficie
+  pub emission: Color,      // Radiancia propia, en espacio lineal
   pub textures: [Option<Texture>; 6],  // Este es un array de texturas
   pub normal_map: Option<Texture>,
+  pub normal_strength: f32,  // Escala la inclinación del normal map; 0 lo anula
+  pub normal_convention: NormalMapConvention,
+  pub texture_filter: TextureFilter,  // Filtro para las texturas y el normal map
+  pub wrap: WrapMode,
+  pub alpha_mode: AlphaMode,
+  pub alpha_cutoff: f32,
 }
 
 
 impl Material {
     pub fn new(
-        diffuse: Color,
-        specular: f32,
-        albedo: [f32; 4],
-        refractive_index: f32,
+        base_color: Color,
+        metallic: f32,
+        roughness: f32,
         textures: [Option<Texture>; 6],   // Recibe un array de 6 texturas opcionales
         normal_map: Option<Texture>,      // Mapa de normales opcional
     ) -> Self {
         Material {
-            diffuse,
-            specular,
-            albedo: albedo.to_vec(),
-            refractive_index,
+            base_color,
+            metallic,
+            roughness,
+            ior: DEFAULT_IOR,
+            transmission: 0.0,
+            emission: Color::black(),
             textures,
             normal_map,
+            normal_strength: 1.0,
+            normal_convention: NormalMapConvention::OpenGl,
+            texture_filter: TextureFilter::Nearest,
+            wrap: WrapMode::Clamp,
+            alpha_mode: AlphaMode::Opaque,
+            alpha_cutoff: 0.5,
         }
     }
 
-    pub fn black() -> Self {
+    // Traducción del formato anterior: un exponente de Phong y pesos en albedo
+    // ([difuso, especular, reflexión, transparencia]). Lo que no se refleja se reparte
+    // entre difuso y transmisión en la misma proporción que albedo[0] y albedo[3], y su
+    // suma escala el color base. El exponente da una rugosidad con un brillo de ancho
+    // parecido y albedo[2] pule la superficie para que se vean los reflejos; sin índice de
+    // refracción el reflejo no dependía del ángulo, como en un metal. albedo[1] no tiene
+    // equivalente: el brillo de un dieléctrico lo fija su índice de refracción.
+    pub fn from_albedo(
+        diffuse: Color,
+        specular: f32,
+        albedo: [f32; 4],
+        refractive_index: f32,
+        textures: [Option<Texture>; 6],
+        normal_map: Option<Texture>,
+    ) -> Self {
+        let reflection = albedo[2].clamp(0.0, 1.0);
+        let diffuse_weight = albedo[0].max(0.0);
+        let transmission_weight = albedo[3].clamp(0.0, 1.0);
+        let weight = diffuse_weight + transmission_weight;
+        let transmission = if weight > 0.0 { transmission_weight / weight } else { 0.0 };
+
+        let color = if textures.iter().any(Option::is_some) { Color::rgb(1.0, 1.0, 1.0) } else { diffuse };
+        let base_color = color * weight.min(1.0);
+
+        // Equivalencia usual entre Phong y GGX: alpha = sqrt(2 / (n + 2)) y alpha = roughness^2
+        let roughness = (2.0 / (specular.max(0.0) + 2.0)).powf(0.25) * (1.0 - reflection);
+
+        let has_ior = refractive_index >= 1.0;
+        let metallic = if has_ior { 0.0 } else { reflection };
+
+        let mut material = Material::new(base_color, metallic, roughness, textures, normal_map);
+        material.ior = if has_ior { refractive_index } else { DEFAULT_IOR };
+        material.transmission = transmission;
+        material
+    }
+
+    pub const fn black() -> Self {
         Material {
-            diffuse: Color::new(0, 0, 0),
-            specular: 0.0,
-            albedo: vec![0.0, 0.0, 0.0, 0.0],
-            refractive_index: 0.0,
+            base_color: Color::black(),
+            metallic: 0.0,
+            roughness: 1.0,
+            ior: DEFAULT_IOR,
+            transmission: 0.0,
+            emission: Color::black(),
             textures: [None, None, None, None, None, None],  // Sin texturas por defecto
             normal_map: None,
+            normal_strength: 1.0,
+            normal_convention: NormalMapConvention::OpenGl,
+            texture_filter: TextureFilter::Nearest,
+            wrap: WrapMode::Clamp,
+            alpha_mode: AlphaMode::Opaque,
+            alpha_cutoff: 0.5,
+        }
+    }
+
+    // Prueba de alfa durante el recorrido: None si el texel es transparente y el rayo debe
+    // seguir de largo; si no, la opacidad (solo menor que 1 con AlphaMode::Blend). Los
+    // materiales opacos no leen la textura.
+    pub fn alpha_at(&self, face_index: usize, u: f32, v: f32, footprint: f32) -> Option<f32> {
+        if self.alpha_mode == AlphaMode::Opaque {
+            return Some(1.0);
+        }
+
+        let alpha = match &self.textures[face_index] {
+            Some(texture) => texture.sample(u, v, footprint, self.texture_filter, self.wrap)[3],
+            None => 1.0,
+        };
+
+        match self.alpha_mode {
+            AlphaMode::Cutout if alpha < self.alpha_cutoff => None,
+            // Con menos de medio nivel de 8 bits no queda nada que mezclar
+            AlphaMode::Blend if alpha < 0.5 / 255.0 => None,
+            AlphaMode::Blend => Some(alpha),
+            _ => Some(1.0),
+        }
+    }
+
+    // Color base y normal de sombreado en el punto del impacto: el color base multiplica
+    // a la textura de la cara y la normal se ajusta con el normal map si existe. La huella
+    // del cono elige el nivel de mipmap.
+    pub fn shade_at(&self, hit: &Intersect) -> (Color, Vec3) {
+        let (u, v, footprint) = (hit.uv.x, hit.uv.y, hit.footprint);
+        let mut normal = hit.normal;
+
+        let base_color = match &self.textures[hit.face_index] {
+            Some(texture) => {
+                let texel = texture.sample(u, v, footprint, self.texture_filter, self.wrap);
+                self.base_color * Color::rgb(texel[0], texel[1], texel[2])
+            }
+            None => self.base_color,
+        };
+
+        // Ajustar la normal con el normal map si está disponible
+        if let Some(normal_map) = &self.normal_map {
+            let texel = normal_map.sample(u, v, footprint, self.texture_filter, self.wrap);
+
+            let normal_tangent = Vec3::new(
+                (texel[0] * 2.0 - 1.0) * self.normal_strength,
+                (texel[1] * 2.0 - 1.0) * self.normal_strength,
+                texel[2] * 2.0 - 1.0,
+            )
+            .normalize();
+
+            let (tangent, bitangent) = self.tangent_frame(hit);
+
+            normal = (tangent * normal_tangent.x
+                + bitangent * normal_tangent.y
+                + normal * normal_tangent.z)
+                .normalize();
+        }
+
+        (base_color, normal)
+    }
+
+    // Base ortonormal del espacio tangente: el eje x sigue a u y el eje y apunta hacia
+    // arriba en la imagen (hacia abajo con DirectX). Si las UV están espejadas, la base también.
+    fn tangent_frame(&self, hit: &Intersect) -> (Vec3, Vec3) {
+        let normal = hit.normal;
+
+        let mut tangent = hit.tangent - normal * normal.dot(&hit.tangent);
+        if tangent.magnitude_squared() < 1e-12 {
+            // Sin UV utilizables: cualquier dirección perpendicular sirve
+            let helper = if normal.x.abs() < 0.9 { Vec3::x() } else { Vec3::y() };
+            tangent = helper.cross(&normal);
+        }
+        let tangent = tangent.normalize();
+
+        // v crece hacia abajo en la imagen
+        let mut down = normal.cross(&tangent);
+        if down.dot(&hit.bitangent) < 0.0 {
+            down = -down;
+        }
+
+        match self.normal_convention {
+            NormalMapConvention::OpenGl => (tangent, -down),
+            NormalMapConvention::DirectX => (tangent, down),
         }
     }
 }
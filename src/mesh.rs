@@ -0,0 +1,154 @@
+use nalgebra_glm::{Vec2, Vec3};
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::ray_intersect::{Intersect, RayIntersect};
+
+// Las mallas tienen una sola superficie: usan la textura de la cara 0 del material
+const SURFACE_INDEX: usize = 0;
+const EPSILON: f32 = 1e-7;
+
+// Índices de un vértice dentro de los arreglos de la malla
+#[derive(Debug, Clone, Copy)]
+pub struct Vertex {
+    pub position: usize,
+    pub normal: Option<usize>,
+    pub uv: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Face {
+    pub vertices: [Vertex; 3],
+    pub material: usize,
+}
+
+// Datos compartidos por todos los triángulos de una malla
+pub struct Mesh {
+    pub positions: Vec<Vec3>,
+    pub normals: Vec<Vec3>,
+    pub uvs: Vec<Vec2>,
+    pub faces: Vec<Face>,
+    pub materials: Vec<Arc<Material>>,
+}
+
+impl Mesh {
+    // Cada triángulo es un objeto de la escena, así la BVH de la escena también acelera la malla
+    pub fn into_triangles(self) -> Vec<Box<dyn RayIntersect>> {
+        let mesh = Arc::new(self);
+        (0..mesh.faces.len())
+            .map(|index| Box::new(Triangle { mesh: Arc::clone(&mesh), index }) as Box<dyn RayIntersect>)
+            .collect()
+    }
+}
+
+pub struct Triangle {
+    pub mesh: Arc<Mesh>,
+    pub index: usize,
+}
+
+impl Triangle {
+    fn corners(&self) -> [Vec3; 3] {
+        let face = &self.mesh.faces[self.index];
+        [
+            self.mesh.positions[face.vertices[0].position],
+            self.mesh.positions[face.vertices[1].position],
+            self.mesh.positions[face.vertices[2].position],
+        ]
+    }
+}
+
+impl RayIntersect for Triangle {
+    // Möller–Trumbore
+    fn ray_intersect(&self, ray: &Ray) -> Intersect<'_> {
+        let ray_origin = &ray.origin;
+        let ray_direction = &ray.direction;
+        let [p0, p1, p2] = self.corners();
+        let edge1 = p1 - p0;
+        let edge2 = p2 - p0;
+
+        let h = ray_direction.cross(&edge2);
+        let det = edge1.dot(&h);
+        if det.abs() < EPSILON {
+            return Intersect::empty();
+        }
+        // det < 0: el rayo llega por detrás del triángulo, según el orden de sus vértices
+        if det < 0.0 && ray.culls_back_faces() {
+            return Intersect::empty();
+        }
+
+        let inv_det = 1.0 / det;
+        let s = ray_origin - p0;
+        let b1 = s.dot(&h) * inv_det;
+        if !(0.0..=1.0).contains(&b1) {
+            return Intersect::empty();
+        }
+
+        let q = s.cross(&edge1);
+        let b2 = ray_direction.dot(&q) * inv_det;
+        if b2 < 0.0 || b1 + b2 > 1.0 {
+            return Intersect::empty();
+        }
+
+        let distance = edge2.dot(&q) * inv_det;
+        if distance <= ray.t_min.max(EPSILON) || distance > ray.t_max {
+            return Intersect::empty();
+        }
+
+        let face = &self.mesh.faces[self.index];
+        let b0 = 1.0 - b1 - b2;
+        let point = ray_origin + ray_direction * distance;
+
+        // Normal suavizada si hay normales por vértice; si no, la del plano
+        let geometric_normal = edge1.cross(&edge2).normalize();
+        let normal = match (face.vertices[0].normal, face.vertices[1].normal, face.vertices[2].normal) {
+            (Some(n0), Some(n1), Some(n2)) => {
+                let normals = &self.mesh.normals;
+                (normals[n0] * b0 + normals[n1] * b1 + normals[n2] * b2).normalize()
+            }
+            _ => geometric_normal,
+        };
+
+        // Sin UV se usan las baricéntricas, cuyo triángulo mide 0.5 en UV. Fuera de [0, 1]
+        // las UV se resuelven con el modo de repetición del material
+        let world_area = edge1.cross(&edge2).magnitude() * 0.5;
+        let ((u, v), uv_area, (tangent, bitangent)) = match (face.vertices[0].uv, face.vertices[1].uv, face.vertices[2].uv) {
+            (Some(t0), Some(t1), Some(t2)) => {
+                let uvs = &self.mesh.uvs;
+                let uv = uvs[t0] * b0 + uvs[t1] * b1 + uvs[t2] * b2;
+                let (e1, e2) = (uvs[t1] - uvs[t0], uvs[t2] - uvs[t0]);
+                let determinant = e1.x * e2.y - e1.y * e2.x;
+
+                // Derivadas del punto respecto de u y v, despejadas de los lados del triángulo
+                let axes = if determinant.abs() > 1e-12 {
+                    let tangent = (edge1 * e2.y - edge2 * e1.y) / determinant;
+                    let bitangent = (edge2 * e1.x - edge1 * e2.x) / determinant;
+                    (tangent, -bitangent)
+                } else {
+                    (Vec3::zeros(), Vec3::zeros())
+                };
+
+                // En OBJ v crece hacia arriba; en las texturas la fila 0 es la de arriba
+                ((uv.x, 1.0 - uv.y), determinant.abs() * 0.5, axes)
+            }
+            _ => ((b1, b2), 0.5, (edge1, edge2)),
+        };
+
+        let world_per_uv = (world_area / uv_area.max(1e-12)).sqrt();
+        let footprint = ray.cone.uv_footprint(distance, ray_direction, &geometric_normal, world_per_uv);
+        let material = &self.mesh.materials[face.material];
+        match Intersect::surface(material, point, normal, distance, SURFACE_INDEX, Vec2::new(u, v), footprint) {
+            Some(hit) => Intersect { geometric_normal, ..hit.with_tangents(tangent, bitangent) },
+            None => Intersect::empty(),
+        }
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let mut bounds = Aabb::empty();
+        for corner in &self.corners() {
+            bounds.grow(corner);
+        }
+        bounds
+    }
+}
@@ -0,0 +1,290 @@
+use nalgebra_glm::{Mat4, Vec2, Vec3};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::assets::AssetCache;
+use crate::color::Color;
+use crate::material::Material;
+use crate::mesh::{Face, Mesh, Vertex};
+use crate::texture::{Texture, WrapMode};
+use crate::transform::{normal_matrix, transform_point};
+
+// Valores de MTL que se traducen a Material
+struct MtlEntry {
+    diffuse: [f32; 3],       // Kd
+    specular: [f32; 3],      // Ks
+    shininess: f32,          // Ns
+    dissolve: f32,           // d (1 = opaco)
+    refractive_index: f32,   // Ni
+    reflective: bool,        // illum 3 o superior
+    diffuse_map: Option<String>,  // map_Kd
+    clamp: bool,                  // -clamp on en map_Kd; si no, las UV se repiten
+    normal_map: Option<String>,   // norm: mapa de normales en espacio tangente
+    bump_map: Option<String>,     // map_Bump / bump: mapa de alturas en escala de grises
+    bump_multiplier: f32,         // -bm en cualquiera de los dos
+    // Extensión PBR de los .mtl: si aparecen, reemplazan lo deducido de Ks, Ns e illum
+    roughness: Option<f32>,  // Pr
+    metallic: Option<f32>,   // Pm
+    emission: [f32; 3],      // Ke
+}
+
+impl MtlEntry {
+    fn new() -> Self {
+        MtlEntry {
+            diffuse: [0.8, 0.8, 0.8],
+            specular: [0.0, 0.0, 0.0],
+            shininess: 10.0,
+            dissolve: 1.0,
+            refractive_index: 1.0,
+            reflective: false,
+            diffuse_map: None,
+            clamp: false,
+            normal_map: None,
+            bump_map: None,
+            bump_multiplier: 1.0,
+            roughness: None,
+            metallic: None,
+            emission: [0.0, 0.0, 0.0],
+        }
+    }
+
+    fn to_material(&self, assets: &mut AssetCache) -> Result<Material, String> {
+        let specular_weight = self.specular.iter().cloned().fold(0.0, f32::max);
+
+        // albedo: [difuso, especular, reflexión, transparencia]
+        let albedo = [
+            1.0,
+            specular_weight,
+            if self.reflective { specular_weight } else { 0.0 },
+            1.0 - self.dissolve.clamp(0.0, 1.0),
+        ];
+
+        let mut textures: [Option<Texture>; 6] = Default::default();
+        if let Some(path) = &self.diffuse_map {
+            textures[0] = Some(assets.texture(path, true)?);
+        }
+
+        // Si hay los dos, manda el mapa de normales; el de alturas se convierte a normales
+        let normal_map = match (&self.normal_map, &self.bump_map) {
+            (Some(path), _) => Some(assets.texture(path, false)?),
+            (None, Some(path)) => Some(assets.height_map(path)?),
+            (None, None) => None,
+        };
+
+        let mut material = Material::from_albedo(
+            // Kd ya está en espacio lineal
+            Color::rgb(self.diffuse[0], self.diffuse[1], self.diffuse[2]),
+            self.shininess,
+            albedo,
+            self.refractive_index,
+            textures,
+            normal_map,
+        );
+        if let Some(roughness) = self.roughness {
+            material.roughness = roughness;
+        }
+        if let Some(metallic) = self.metallic {
+            material.metallic = metallic;
+        }
+        material.emission = Color::rgb(self.emission[0], self.emission[1], self.emission[2]);
+        material.normal_strength = self.bump_multiplier;
+        material.wrap = if self.clamp { WrapMode::Clamp } else { WrapMode::Repeat };
+
+        Ok(material)
+    }
+}
+
+fn error_at(path: &Path, line: usize, message: &str) -> String {
+    format!("{}: línea {}: {}", path.display(), line, message)
+}
+
+fn parse_floats<const N: usize>(parts: &[&str], path: &Path, line: usize) -> Result<[f32; N], String> {
+    if parts.len() < N {
+        return Err(error_at(path, line, &format!("Se esperaban {} números", N)));
+    }
+    let mut values = [0.0; N];
+    for (value, part) in values.iter_mut().zip(parts) {
+        *value = part
+            .parse()
+            .map_err(|_| error_at(path, line, &format!("Número inválido '{}'", part)))?;
+    }
+    Ok(values)
+}
+
+// Índice de OBJ (desde 1, o negativo relativo al final) a índice desde 0
+fn resolve_index(token: &str, count: usize, path: &Path, line: usize) -> Result<usize, String> {
+    let index: i64 = token
+        .parse()
+        .map_err(|_| error_at(path, line, &format!("Índice inválido '{}'", token)))?;
+    let resolved = if index < 0 { count as i64 + index } else { index - 1 };
+    if resolved < 0 || resolved >= count as i64 {
+        return Err(error_at(path, line, &format!("Índice {} fuera de rango", index)));
+    }
+    Ok(resolved as usize)
+}
+
+fn load_mtl(path: &Path) -> Result<HashMap<String, MtlEntry>, String> {
+    let source = std::fs::read_to_string(path)
+        .map_err(|e| format!("Error al leer la biblioteca de materiales {}: {}", path.display(), e))?;
+    let directory = path.parent().unwrap_or(Path::new(""));
+    let texture_path = |name: &str| directory.join(name).to_string_lossy().into_owned();
+
+    let mut entries = HashMap::new();
+    let mut current: Option<(String, MtlEntry)> = None;
+
+    for (number, raw) in source.lines().enumerate() {
+        let line = number + 1;
+        let parts: Vec<&str> = raw.split_whitespace().collect();
+        let Some((&keyword, args)) = parts.split_first() else {
+            continue;
+        };
+        if keyword.starts_with('#') {
+            continue;
+        }
+
+        if keyword == "newmtl" {
+            if let Some((name, entry)) = current.take() {
+                entries.insert(name, entry);
+            }
+            let name = args.join(" ");
+            current = Some((name, MtlEntry::new()));
+            continue;
+        }
+
+        let Some((_, entry)) = current.as_mut() else {
+            return Err(error_at(path, line, &format!("'{}' antes de cualquier newmtl", keyword)));
+        };
+
+        match keyword {
+            "Kd" => entry.diffuse = parse_floats::<3>(args, path, line)?,
+            "Ks" => entry.specular = parse_floats::<3>(args, path, line)?,
+            "Ns" => entry.shininess = parse_floats::<1>(args, path, line)?[0],
+            "Ni" => entry.refractive_index = parse_floats::<1>(args, path, line)?[0],
+            "d" => entry.dissolve = parse_floats::<1>(args, path, line)?[0],
+            "Tr" => entry.dissolve = 1.0 - parse_floats::<1>(args, path, line)?[0],
+            "Pr" => entry.roughness = Some(parse_floats::<1>(args, path, line)?[0]),
+            "Pm" => entry.metallic = Some(parse_floats::<1>(args, path, line)?[0]),
+            "Ke" => entry.emission = parse_floats::<3>(args, path, line)?,
+            "illum" => entry.reflective = parse_floats::<1>(args, path, line)?[0] >= 3.0,
+            // Las opciones (-bm, -s, ...) van antes del nombre; el archivo es el último argumento
+            "map_Kd" => {
+                entry.diffuse_map = args.last().map(|name| texture_path(name));
+                entry.clamp = args.windows(2).any(|pair| pair == ["-clamp", "on"]);
+            }
+            "norm" | "map_Bump" | "bump" => {
+                let map = args.last().map(|name| texture_path(name));
+                if keyword == "norm" {
+                    entry.normal_map = map;
+                } else {
+                    entry.bump_map = map;
+                }
+                if let Some(position) = args.iter().position(|arg| *arg == "-bm") {
+                    entry.bump_multiplier = parse_floats::<1>(&args[position + 1..], path, line)?[0];
+                }
+            }
+            _ => {}  // Ka, map_Ks, etc. no tienen equivalente en Material
+        }
+    }
+
+    if let Some((name, entry)) = current.take() {
+        entries.insert(name, entry);
+    }
+
+    Ok(entries)
+}
+
+// Carga un OBJ (con su MTL si lo declara). Los polígonos se triangulan en abanico.
+// La transformación se aplica a los vértices al cargar para colocar el modelo en la escena.
+pub fn load_obj(path: &str, transform: &Mat4, assets: &mut AssetCache) -> Result<Mesh, String> {
+    let path = Path::new(path);
+    let source = std::fs::read_to_string(path)
+        .map_err(|e| format!("Error al leer el modelo {}: {}", path.display(), e))?;
+    let directory = path.parent().unwrap_or(Path::new(""));
+    let normal_transform = normal_matrix(transform);
+
+    let mut mesh = Mesh {
+        positions: Vec::new(),
+        normals: Vec::new(),
+        uvs: Vec::new(),
+        faces: Vec::new(),
+        materials: vec![Arc::new(MtlEntry::new().to_material(assets)?)],
+    };
+
+    let mut library: HashMap<String, MtlEntry> = HashMap::new();
+    let mut material_ids: HashMap<String, usize> = HashMap::new();
+    let mut current_material = 0;
+
+    for (number, raw) in source.lines().enumerate() {
+        let line = number + 1;
+        let parts: Vec<&str> = raw.split_whitespace().collect();
+        let Some((&keyword, args)) = parts.split_first() else {
+            continue;
+        };
+
+        match keyword {
+            "v" => {
+                let [x, y, z] = parse_floats::<3>(args, path, line)?;
+                mesh.positions.push(transform_point(transform, &Vec3::new(x, y, z)));
+            }
+            "vn" => {
+                let [x, y, z] = parse_floats::<3>(args, path, line)?;
+                mesh.normals.push((normal_transform * Vec3::new(x, y, z)).normalize());
+            }
+            "vt" => {
+                let [u, v] = parse_floats::<2>(args, path, line)?;
+                mesh.uvs.push(Vec2::new(u, v));
+            }
+            "f" => {
+                if args.len() < 3 {
+                    return Err(error_at(path, line, "Una cara necesita al menos 3 vértices"));
+                }
+
+                let mut vertices = Vec::with_capacity(args.len());
+                for token in args {
+                    let mut indices = token.split('/');
+                    let position = resolve_index(indices.next().unwrap_or(""), mesh.positions.len(), path, line)?;
+                    let uv = match indices.next() {
+                        Some(t) if !t.is_empty() => Some(resolve_index(t, mesh.uvs.len(), path, line)?),
+                        _ => None,
+                    };
+                    let normal = match indices.next() {
+                        Some(n) if !n.is_empty() => Some(resolve_index(n, mesh.normals.len(), path, line)?),
+                        _ => None,
+                    };
+                    vertices.push(Vertex { position, normal, uv });
+                }
+
+                for i in 1..vertices.len() - 1 {
+                    mesh.faces.push(Face {
+                        vertices: [vertices[0], vertices[i], vertices[i + 1]],
+                        material: current_material,
+                    });
+                }
+            }
+            "mtllib" => {
+                for name in args {
+                    library.extend(load_mtl(&directory.join(name))?);
+                }
+            }
+            "usemtl" => {
+                let name = args.join(" ");
+                current_material = match material_ids.get(&name) {
+                    Some(&id) => id,
+                    None => {
+                        let entry = library
+                            .get(&name)
+                            .ok_or_else(|| error_at(path, line, &format!("Material desconocido '{}'", name)))?;
+                        let material = entry.to_material(assets).map_err(|e| error_at(path, line, &e))?;
+                        mesh.materials.push(Arc::new(material));
+                        material_ids.insert(name, mesh.materials.len() - 1);
+                        mesh.materials.len() - 1
+                    }
+                };
+            }
+            _ => {}  // o, g, s y comentarios no afectan la geometría
+        }
+    }
+
+    Ok(mesh)
+}
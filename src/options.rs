@@ -0,0 +1,125 @@
+use crate::filter::Filter;
+use crate::scene_file::RenderSettings;
+use crate::tonemap::ToneMapping;
+
+pub const USAGE: &str = "\
+Uso: GRAFICAS-PROYECTO2 [opciones]
+
+Opciones:
+  --headless             Renderiza un solo cuadro a PNG sin abrir ventana
+  --scene <ruta>         Archivo de escena TOML (por defecto scenes/diorama.toml)
+  --width <px>           Ancho de la imagen (por defecto el de la escena)
+  --height <px>          Alto de la imagen (por defecto el de la escena)
+  --output <ruta>        Archivo de salida en modo headless (por defecto render.png)
+  --light-angle <grados> Ángulo fijo del sol en su órbita; la luna queda opuesta
+  --threads <n>          Hilos de render; 0 usa todos los núcleos, 1 renderiza en un solo hilo
+  --exposure <k>         Multiplicador de exposición antes del tone mapping
+  --tone-mapping <op>    clamp, reinhard o aces
+  --samples <n>          Muestras por píxel para el antialiasing (1 lo desactiva)
+  --filter <f>           Filtro de reconstrucción: box, tent o mitchell
+  --shadow-samples <n>   Rayos de sombra por luz con tamaño (sombras suaves)
+  --bench-bvh <cubos>    Compara la BVH contra la búsqueda lineal en una rejilla de cubos
+  --help                 Muestra este mensaje";
+
+pub struct Options {
+    pub headless: bool,
+    pub scene: String,
+    pub width: Option<usize>,
+    pub height: Option<usize>,
+    pub output: String,
+    pub light_angle: Option<f32>,  // En grados
+    pub threads: Option<usize>,
+    pub exposure: Option<f32>,
+    pub tone_mapping: Option<ToneMapping>,
+    pub samples: Option<usize>,
+    pub filter: Option<Filter>,
+    pub shadow_samples: Option<usize>,
+    pub bench_bvh: Option<usize>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            headless: false,
+            scene: String::from("scenes/diorama.toml"),
+            width: None,
+            height: None,
+            output: String::from("render.png"),
+            light_angle: None,
+            threads: None,
+            exposure: None,
+            tone_mapping: None,
+            samples: None,
+            filter: None,
+            shadow_samples: None,
+            bench_bvh: None,
+        }
+    }
+}
+
+impl Options {
+    // Lee las opciones de la línea de comandos (sin el nombre del programa)
+    pub fn parse<I: Iterator<Item = String>>(mut args: I) -> Result<Self, String> {
+        let mut options = Options::default();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--headless" => options.headless = true,
+                "--scene" => options.scene = parse_value(&arg, args.next())?,
+                "--width" => options.width = Some(parse_value(&arg, args.next())?),
+                "--height" => options.height = Some(parse_value(&arg, args.next())?),
+                "--output" => options.output = parse_value(&arg, args.next())?,
+                "--light-angle" => options.light_angle = Some(parse_value(&arg, args.next())?),
+                "--threads" => options.threads = Some(parse_value(&arg, args.next())?),
+                "--exposure" => options.exposure = Some(parse_value(&arg, args.next())?),
+                "--tone-mapping" => options.tone_mapping = Some(parse_value(&arg, args.next())?),
+                "--samples" => options.samples = Some(parse_value(&arg, args.next())?),
+                "--filter" => options.filter = Some(parse_value(&arg, args.next())?),
+                "--shadow-samples" => options.shadow_samples = Some(parse_value(&arg, args.next())?),
+                "--bench-bvh" => options.bench_bvh = Some(parse_value(&arg, args.next())?),
+                "--help" | "-h" => return Err(String::new()),
+                _ => return Err(format!("Opción desconocida: {}", arg)),
+            }
+        }
+
+        if options.width == Some(0) || options.height == Some(0) {
+            return Err(String::from("La resolución debe ser mayor que cero"));
+        }
+        if options.samples == Some(0) || options.shadow_samples == Some(0) {
+            return Err(String::from("El número de muestras debe ser mayor que cero"));
+        }
+
+        Ok(options)
+    }
+}
+
+impl Options {
+    // Las opciones de la línea de comandos tienen prioridad sobre las de la escena
+    pub fn apply(&self, settings: &mut RenderSettings) {
+        if let Some(threads) = self.threads {
+            settings.threads = threads;
+        }
+        if let Some(exposure) = self.exposure {
+            settings.exposure = exposure;
+        }
+        if let Some(tone_mapping) = self.tone_mapping {
+            settings.tone_mapping = tone_mapping;
+        }
+        if let Some(samples) = self.samples {
+            settings.samples = samples;
+        }
+        if let Some(filter) = self.filter {
+            settings.filter = filter;
+        }
+        if let Some(shadow_samples) = self.shadow_samples {
+            settings.shadow_samples = shadow_samples;
+        }
+    }
+}
+
+fn parse_value<T: std::str::FromStr>(flag: &str, value: Option<String>) -> Result<T, String> {
+    let value = value.ok_or_else(|| format!("Falta el valor para {}", flag))?;
+    value
+        .parse()
+        .map_err(|_| format!("Valor inválido para {}: {}", flag, value))
+}
@@ -0,0 +1,84 @@
+use nalgebra_glm::Vec3;
+
+// Para qué se lanza un rayo; decide, entre otras cosas, si ve las caras traseras
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RayKind {
+    Primary,     // Desde la cámara
+    Shadow,      // Hacia una luz
+    Reflection,
+    Refraction,
+}
+
+// Cono que envuelve al rayo, para elegir el nivel de mipmap de las texturas.
+// width es el ancho del haz en el origen y spread cuánto se abre por unidad de distancia.
+#[derive(Debug, Clone, Copy)]
+pub struct RayCone {
+    pub width: f32,
+    pub spread: f32,
+}
+
+impl RayCone {
+    pub fn new(width: f32, spread: f32) -> Self {
+        RayCone { width, spread }
+    }
+
+    pub fn width_at(&self, distance: f32) -> f32 {
+        self.width + self.spread * distance
+    }
+
+    // Cono de un rayo secundario que sale del punto a esa distancia
+    pub fn advance(&self, distance: f32) -> RayCone {
+        RayCone::new(self.width_at(distance), self.spread)
+    }
+
+    // Ancho del haz sobre la superficie en unidades UV. world_per_uv es cuánto mide en el
+    // mundo una unidad de UV; en ángulos rasantes la huella se alarga.
+    pub fn uv_footprint(&self, distance: f32, direction: &Vec3, normal: &Vec3, world_per_uv: f32) -> f32 {
+        let cosine = (normal.dot(direction) / direction.magnitude()).abs().max(0.1);
+        self.width_at(distance) / cosine / world_per_uv
+    }
+}
+
+// Rayo con el intervalo [t_min, t_max] donde cuentan los impactos. La dirección no tiene
+// que ser unitaria: t se mide en múltiplos de ella.
+#[derive(Debug, Clone, Copy)]
+pub struct Ray {
+    pub origin: Vec3,
+    pub direction: Vec3,
+    pub t_min: f32,
+    pub t_max: f32,
+    pub kind: RayKind,
+    pub cone: RayCone,
+}
+
+impl Ray {
+    pub fn new(origin: Vec3, direction: Vec3, kind: RayKind, cone: RayCone) -> Self {
+        Ray {
+            origin,
+            direction,
+            t_min: 0.0,
+            t_max: f32::INFINITY,
+            kind,
+            cone,
+        }
+    }
+
+    pub fn with_max_distance(self, t_max: f32) -> Self {
+        Ray { t_max, ..self }
+    }
+
+    pub fn at(&self, t: f32) -> Vec3 {
+        self.origin + self.direction * t
+    }
+
+    // Las caras cuya normal apunta en la dirección del rayo se descartan solo en los rayos
+    // primarios: si la cámara queda dentro de un sólido, se ve a través. Los demás tienen que
+    // ver las caras por las que salen de un sólido (la reflexión total interna, por ejemplo).
+    pub fn culls_back_faces(&self) -> bool {
+        self.kind == RayKind::Primary
+    }
+
+    pub fn is_back_face(&self, normal: &Vec3) -> bool {
+        self.culls_back_faces() && normal.dot(&self.direction) >= 0.0
+    }
+}
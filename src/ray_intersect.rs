@@ -1,43 +1,96 @@
 
-use nalgebra_glm::Vec3;
+use nalgebra_glm::{Vec2, Vec3};
+use crate::aabb::Aabb;
+use crate::color::Color;
 use crate::material::Material;
-use std::rc::Rc;
-use std::sync::Arc;
+use crate::ray::Ray;
 
+// Material de los rayos que no chocan; estático para que un fallo no reserve memoria
+static EMPTY_MATERIAL: Material = Material::black();
 
-
-#[derive(Debug, Clone,)]
-#[allow(dead_code)]
-pub struct Intersect {
+// Registro de un impacto. No copia el material: lo toma prestado del objeto y guarda lo
+// necesario (cara, UV, huella del cono) para muestrear las texturas solo en el impacto
+// más cercano, con shade().
+#[derive(Debug, Clone, Copy)]
+pub struct Intersect<'a> {
     pub point: Vec3,
-    pub normal: Vec3,
+    pub normal: Vec3,            // Normal de sombreado (interpolada), antes del normal map
+    pub geometric_normal: Vec3,  // Normal real de la superficie
     pub distance: f32,
     pub is_intersecting: bool,
-    pub material: Rc<Material>,
+    pub object_id: usize,        // Índice del objeto en la escena, en el orden en que se agregó; lo pone la BVH
+    pub face_index: usize,
+    pub uv: Vec2,
+    pub tangent: Vec3,           // Hacia dónde crecen u y v en el mundo (sin normalizar);
+    pub bitangent: Vec3,         // cero si la superficie no los define
+    pub footprint: f32,          // Ancho del cono del rayo en unidades UV
+    pub opacity: f32,            // Alfa en el punto; solo es menor que 1 con AlphaMode::Blend
+    pub material: &'a Material,
 }
 
-impl Intersect {
-    pub fn new(point: Vec3, normal: Vec3, distance: f32, material: Rc<Material>) -> Self {
-        Intersect {
+impl<'a> Intersect<'a> {
+    // Impacto sobre una superficie con material. Hace la prueba de alfa: devuelve None si el
+    // texel es transparente y el rayo debe seguir de largo.
+    pub fn surface(
+        material: &'a Material,
+        point: Vec3,
+        normal: Vec3,
+        distance: f32,
+        face_index: usize,
+        uv: Vec2,
+        footprint: f32,
+    ) -> Option<Self> {
+        let opacity = material.alpha_at(face_index, uv.x, uv.y, footprint)?;
+
+        Some(Intersect {
             point,
             normal,
+            geometric_normal: normal,
             distance,
             is_intersecting: true,
+            object_id: 0,
+            face_index,
+            uv,
+            tangent: Vec3::zeros(),
+            bitangent: Vec3::zeros(),
+            footprint,
+            opacity,
             material,
-        }
+        })
     }
 
     pub fn empty() -> Self {
         Intersect {
             point: Vec3::zeros(),
             normal: Vec3::zeros(),
+            geometric_normal: Vec3::zeros(),
             distance: 0.0,
             is_intersecting: false,
-            material: Rc::new(Material::black()),
+            object_id: 0,
+            face_index: 0,
+            uv: Vec2::zeros(),
+            tangent: Vec3::zeros(),
+            bitangent: Vec3::zeros(),
+            footprint: 0.0,
+            opacity: 1.0,
+            material: &EMPTY_MATERIAL,
         }
     }
+
+    // Derivadas del punto respecto de u y v, para orientar el normal map
+    pub fn with_tangents(self, tangent: Vec3, bitangent: Vec3) -> Self {
+        Intersect { tangent, bitangent, ..self }
+    }
+
+    // Color difuso y normal de sombreado, muestreando las texturas del material
+    pub fn shade(&self) -> (Color, Vec3) {
+        self.material.shade_at(self)
+    }
 }
 
-pub trait RayIntersect {
-    fn ray_intersect(&self, ray_origin: &Vec3, ray_direction: &Vec3, camera_position: &Vec3) -> Intersect;
+// Cualquier objeto de la escena: basta con saber intersectarlo y acotarlo con una caja
+pub trait RayIntersect: Send + Sync {
+    fn ray_intersect(&self, ray: &Ray) -> Intersect<'_>;
+
+    fn bounding_box(&self) -> Aabb;
 }
\ No newline at end of file
@@ -0,0 +1,63 @@
+// Generador de números aleatorios (PCG32) para las muestras del render. Cada píxel
+// deriva su propia semilla, así el resultado no depende de qué hilo lo calcula.
+pub struct Sampler {
+    state: u64,
+    increment: u64,
+}
+
+const MULTIPLIER: u64 = 6364136223846793005;
+
+// Mezcla de bits (SplitMix64) para derivar semillas poco correlacionadas
+fn mix(mut z: u64) -> u64 {
+    z = z.wrapping_add(0x9E3779B97F4A7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^ (z >> 31)
+}
+
+impl Sampler {
+    pub fn new(seed: u64, stream: u64) -> Self {
+        let mut sampler = Sampler {
+            state: 0,
+            increment: (stream << 1) | 1,
+        };
+        sampler.next_u32();
+        sampler.state = sampler.state.wrapping_add(seed);
+        sampler.next_u32();
+        sampler
+    }
+
+    // Secuencia propia de un píxel; pass distingue pasadas distintas sobre la misma imagen
+    pub fn for_pixel(x: usize, y: usize, pass: u64) -> Self {
+        let seed = mix(((y as u64) << 32) ^ x as u64);
+        Sampler::new(seed, mix(pass))
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old.wrapping_mul(MULTIPLIER).wrapping_add(self.increment);
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        let rotation = (old >> 59) as u32;
+        xorshifted.rotate_right(rotation)
+    }
+
+    // Número uniforme en [0, 1)
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u32() >> 8) as f32 * (1.0 / (1u32 << 24) as f32)
+    }
+
+    // Muestra index de count, estratificada en una rejilla que cubre el cuadrado unitario
+    // y con una posición aleatoria dentro de su celda
+    pub fn stratified_2d(&mut self, index: usize, count: usize) -> (f32, f32) {
+        let columns = (count as f32).sqrt().ceil() as usize;
+        let rows = count.div_ceil(columns);
+        let row = index / columns;
+        // La última fila puede tener menos celdas; se ensanchan para cubrir todo el ancho
+        let in_row = (count - row * columns).min(columns);
+        let column = index % columns;
+
+        let u = (column as f32 + self.next_f32()) / in_row as f32;
+        let v = (row as f32 + self.next_f32()) / rows as f32;
+        (u, v)
+    }
+}
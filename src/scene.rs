@@ -0,0 +1,87 @@
+use nalgebra_glm::Vec3;
+
+use crate::bvh::Bvh;
+use crate::color::Color;
+use crate::environment::EnvironmentMap;
+use crate::light::{Light, LightKind};
+use crate::ray::Ray;
+use crate::ray_intersect::{Intersect, RayIntersect};
+use crate::sky::{Sky, DEFAULT_SUN_ANGLE};
+
+pub const SKYBOX_COLOR: Color = Color::rgb(0.058, 0.270, 0.776);  // #448EE4 en sRGB
+
+// Lo que ven los rayos que no chocan con nada
+pub enum Background {
+    Color(Color),
+    Sky(Sky),
+    Environment(EnvironmentMap),
+}
+
+// Lo que necesita el integrador: los objetos (de cualquier tipo) detrás de una BVH, las
+// luces y el fondo
+pub struct Scene {
+    objects: Bvh,
+    pub lights: Vec<Light>,
+    pub background: Background,
+}
+
+impl Scene {
+    pub fn new(objects: Vec<Box<dyn RayIntersect>>, lights: Vec<Light>) -> Self {
+        Scene {
+            objects: Bvh::new(objects),
+            lights,
+            background: Background::Color(SKYBOX_COLOR),
+        }
+    }
+
+    // Color del fondo en esa dirección (unitaria)
+    pub fn background(&self, direction: &Vec3) -> Color {
+        match &self.background {
+            Background::Color(color) => *color,
+            Background::Sky(sky) => sky.radiance(direction),
+            Background::Environment(environment) => environment.radiance(direction),
+        }
+    }
+
+    // El cielo sigue a la luz que hace de sol; hay que llamarla cuando las luces se mueven
+    pub fn update_sky(&mut self) {
+        if let Background::Sky(sky) = &mut self.background {
+            let sun = &self.lights[sky.sun_light];
+            let angle = match sun.kind {
+                LightKind::Directional { angle, .. } if angle > 0.0 => angle,
+                _ => DEFAULT_SUN_ANGLE,
+            };
+            // Una luz en el origen no da dirección: el sol queda en el cenit en vez de dar NaN
+            let direction = sun.reference_position().try_normalize(1e-6).unwrap_or_else(Vec3::y);
+            sky.set_sun(direction, angle, sun.color * sun.intensity);
+        }
+    }
+
+    // Intersección más cercana dentro del intervalo del rayo
+    pub fn closest_hit(&self, ray: &Ray) -> Intersect<'_> {
+        self.objects.closest_hit(ray)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra_glm::Vec3;
+
+    use super::{Background, Scene};
+    use crate::color::Color;
+    use crate::light::{Light, LightKind};
+    use crate::sky::Sky;
+
+    #[test]
+    fn sun_at_the_origin_does_not_poison_the_sky() {
+        let lamp = Light::new(LightKind::Point { position: Vec3::zeros(), radius: 0.0 }, Color::rgb(1.0, 1.0, 1.0), 1.0);
+        let mut scene = Scene::new(Vec::new(), vec![lamp]);
+        scene.background = Background::Sky(Sky::new(0, 3.0, 1.0));
+        scene.update_sky();
+
+        for direction in [Vec3::y(), Vec3::x(), Vec3::new(0.3, -0.5, 0.8).normalize()] {
+            let color = scene.background(&direction);
+            assert!(color.r.is_finite() && color.g.is_finite() && color.b.is_finite(), "{:?}", color);
+        }
+    }
+}
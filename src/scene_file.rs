@@ -0,0 +1,855 @@
+use nalgebra_glm::Vec3;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use toml::Spanned;
+
+use crate::assets::AssetCache;
+use crate::camera::Camera;
+use crate::color::Color;
+use crate::cube::Cube;
+use crate::environment::{EnvironmentImage, EnvironmentMap};
+use crate::filter::Filter;
+use crate::light::{orthonormal_basis, Falloff, Light, LightKind};
+use crate::ray_intersect::RayIntersect;
+use crate::scene::{Background, Scene};
+use crate::sky::Sky;
+use crate::sphere::{Ellipsoid, Sphere};
+use crate::material::{AlphaMode, Material, NormalMapConvention};
+use crate::obj_loader::load_obj;
+use crate::texture::{Texture, TextureFilter, WrapMode};
+use crate::tonemap::ToneMapping;
+use crate::transform::{compose, Transformed};
+use crate::voxel_grid::VoxelGrid;
+
+// Estructuras tal como aparecen en el archivo TOML
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneFile {
+    #[serde(default)]
+    render: RenderSection,
+    camera: CameraSection,
+    orbit: Option<OrbitSection>,
+    #[serde(default)]
+    materials: HashMap<String, MaterialSection>,
+    #[serde(default)]
+    cubes: Vec<CubeSection>,
+    #[serde(default)]
+    spheres: Vec<SphereSection>,
+    #[serde(default)]
+    ellipsoids: Vec<EllipsoidSection>,
+    #[serde(default)]
+    meshes: Vec<MeshSection>,
+    #[serde(default)]
+    lights: Vec<Spanned<LightSection>>,
+    voxels: Option<VoxelSection>,
+    sky: Option<Spanned<SkySection>>,
+    environment: Option<Spanned<EnvironmentSection>>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct RenderSection {
+    width: Option<usize>,
+    height: Option<usize>,
+    fov: Option<f32>,
+    light_angle: Option<f32>,
+    threads: Option<usize>,
+    exposure: Option<f32>,
+    tone_mapping: Option<Spanned<String>>,
+    samples: Option<usize>,
+    filter: Option<Spanned<String>>,
+    shadow_samples: Option<usize>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CameraSection {
+    eye: [f32; 3],
+    center: [f32; 3],
+    #[serde(default = "default_up")]
+    up: [f32; 3],
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct OrbitSection {
+    #[serde(default)]
+    center: [f32; 3],
+    radius: f32,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MaterialSection {
+    // Modelo de metalicidad y rugosidad; la emisión es un color sRGB escalado por emission_strength
+    base_color: Option<[u8; 3]>,
+    metallic: Option<Spanned<f32>>,
+    roughness: Option<Spanned<f32>>,
+    ior: Option<Spanned<f32>>,
+    transmission: Option<Spanned<f32>>,
+    emission: Option<[u8; 3]>,
+    emission_strength: Option<f32>,
+    // Formato anterior (Phong con pesos en albedo); se traduce con Material::from_albedo
+    diffuse: Option<Spanned<[u8; 3]>>,
+    specular: Option<Spanned<f32>>,
+    albedo: Option<[f32; 4]>,
+    refractive_index: Option<Spanned<f32>>,
+    // Una sola textura para las seis caras, o una por cara
+    texture: Option<Spanned<String>>,
+    faces: Option<Spanned<Vec<Spanned<String>>>>,
+    normal_map: Option<Spanned<String>>,
+    // Intensidad del normal map y convención del canal verde: opengl o directx
+    normal_strength: Option<f32>,
+    normal_convention: Option<Spanned<String>>,
+    // Muestreo de las texturas: nearest, bilinear o trilinear; y repeat, mirror o clamp
+    texture_filter: Option<Spanned<String>>,
+    wrap: Option<Spanned<String>>,
+    // Uso del alfa de la textura: opaque, cutout (con alpha_cutoff) o blend
+    alpha_mode: Option<Spanned<String>>,
+    alpha_cutoff: Option<f32>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CubeSection {
+    material: Spanned<String>,
+    center: [f32; 3],
+    half_size: [f32; 3],
+    // Transformación opcional alrededor del centro (grados en x, y, z y escala por eje)
+    rotation: Option<[f32; 3]>,
+    scale: Option<[f32; 3]>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SphereSection {
+    material: Spanned<String>,
+    center: [f32; 3],
+    radius: f32,
+    rotation: Option<[f32; 3]>,
+    scale: Option<[f32; 3]>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EllipsoidSection {
+    material: Spanned<String>,
+    center: [f32; 3],
+    radii: [f32; 3],
+    rotation: Option<[f32; 3]>,
+    scale: Option<[f32; 3]>,
+}
+
+// Modelo OBJ; si se indica material, reemplaza a los del MTL
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MeshSection {
+    path: Spanned<String>,
+    #[serde(default = "default_scale")]
+    scale: f32,
+    #[serde(default)]
+    translate: [f32; 3],
+    #[serde(default)]
+    rotation: [f32; 3],
+    material: Option<Spanned<String>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct VoxelSection {
+    origin: [f32; 3],
+    block_size: f32,
+    size: [usize; 3],
+    #[serde(default)]
+    blocks: Vec<BlockSection>,
+}
+
+// Un bloque, o una región de bloques si se indica max (inclusive)
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BlockSection {
+    material: Spanned<String>,
+    min: Spanned<[usize; 3]>,
+    max: Option<[usize; 3]>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LightSection {
+    // point, directional, spot, rect o disk; point si no se indica
+    #[serde(rename = "type")]
+    kind: Option<Spanned<String>>,
+    position: Option<[f32; 3]>,
+    direction: Option<[f32; 3]>,  // Hacia donde viaja la luz o hacia donde emite
+    color: [u8; 3],
+    intensity: f32,
+    orbit_phase: Option<f32>,
+    // Spot: medio ángulo del cono en grados y fracción del borde que se difumina.
+    // Direccional: radio aparente del disco en grados.
+    angle: Option<f32>,
+    blend: Option<f32>,
+    // Área: ancho y alto del rectángulo, o radio del disco. Puntual y spot: radio de la esfera.
+    size: Option<[f32; 2]>,
+    radius: Option<f32>,
+    // none, inverse_square, linear o range; inverse_square si no se indica
+    falloff: Option<Spanned<String>>,
+    range: Option<f32>,  // Distancia a la que se apaga con falloff = "range"
+}
+
+// Cielo procedural; sin esta sección el fondo es un color fijo
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SkySection {
+    sun: Option<Spanned<usize>>,  // Índice de la luz que hace de sol; la primera direccional si no se indica
+    turbidity: Option<Spanned<f32>>,
+    intensity: Option<f32>,
+}
+
+// Mapa de entorno: una imagen equirectangular o las seis caras de un cubemap
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EnvironmentSection {
+    path: Option<Spanned<String>>,
+    faces: Option<Spanned<Vec<Spanned<String>>>>,  // +x, -x, +y, -y, +z, -z
+    #[serde(default)]
+    rotation: f32,  // En grados, alrededor del eje y
+    intensity: Option<f32>,
+}
+
+fn default_up() -> [f32; 3] {
+    [0.0, 1.0, 0.0]
+}
+
+fn default_scale() -> f32 {
+    1.0
+}
+
+// Estructuras que usa el programa una vez cargada la escena
+
+pub struct RenderSettings {
+    pub width: usize,
+    pub height: usize,
+    pub fov: f32,  // En radianes
+    pub light_angle: Option<f32>,  // En grados
+    pub threads: usize,  // 0 = todos los núcleos
+    pub exposure: f32,
+    pub tone_mapping: ToneMapping,
+    pub samples: usize,  // Muestras por píxel
+    pub filter: Filter,
+    pub shadow_samples: usize,  // Rayos de sombra por luz con tamaño, en cada impacto
+}
+
+pub struct Orbit {
+    pub center: Vec3,
+    pub radius: f32,
+    pub phases: Vec<(usize, f32)>,  // (índice de la luz, desfase en radianes)
+}
+
+pub struct LoadedScene {
+    pub scene: Scene,
+    pub camera: Camera,
+    pub orbit: Option<Orbit>,
+    pub settings: RenderSettings,
+}
+
+impl LoadedScene {
+    // Mueve las luces que orbitan según el ángulo del sol, y el cielo con ellas
+    pub fn update_lights(&mut self, angle: f32) {
+        if let Some(orbit) = &self.orbit {
+            for &(index, phase) in &orbit.phases {
+                self.scene.lights[index].update_position_orbit(orbit.center, orbit.radius, angle + phase);
+            }
+        }
+        self.scene.update_sky();
+    }
+}
+
+pub fn load_scene(path: &str) -> Result<LoadedScene, String> {
+    let source = std::fs::read_to_string(path)
+        .map_err(|e| format!("Error al leer la escena {}: {}", path, e))?;
+
+    // Las rutas de texturas y modelos son relativas a la carpeta de la escena
+    let directory = Path::new(path).parent().unwrap_or(Path::new(""));
+    parse_scene(&source, directory).map_err(|e| format!("{}: {}", path, e))
+}
+
+pub fn parse_scene(source: &str, directory: &Path) -> Result<LoadedScene, String> {
+    let file: SceneFile = toml::from_str(source).map_err(|e| format_toml_error(source, &e))?;
+
+    // Las texturas de color de los materiales se leen primero para empaquetarlas juntas en el atlas
+    let mut assets = AssetCache::new();
+    for section in file.materials.values() {
+        let faces = section.faces.iter().flat_map(|faces| faces.get_ref());
+        for path in section.texture.iter().chain(faces) {
+            assets
+                .preload(&resolve(directory, path.get_ref()))
+                .map_err(|e| error_at(source, path.span().start, &e))?;
+        }
+    }
+    assets.build_atlas();
+
+    let mut materials = HashMap::new();
+    for (name, section) in &file.materials {
+        let material = build_material(source, directory, section, &mut assets)?;
+        materials.insert(name.as_str(), Arc::new(material));
+    }
+
+    let mut objects: Vec<Box<dyn RayIntersect>> = Vec::with_capacity(file.cubes.len() + file.spheres.len() + file.ellipsoids.len() + 1);
+    for cube in &file.cubes {
+        let material = find_material(source, &materials, &cube.material)?;
+
+        let transformed = cube.rotation.is_some() || cube.scale.is_some();
+
+        let object = Box::new(Cube {
+            center: if transformed { Vec3::zeros() } else { to_vec3(cube.center) },
+            dim_x: cube.half_size[0],
+            dim_y: cube.half_size[1],
+            dim_z: cube.half_size[2],
+            material: Arc::clone(material),
+        });
+        objects.push(place(object, cube.center, cube.rotation, cube.scale));
+    }
+
+    for sphere in &file.spheres {
+        let material = find_material(source, &materials, &sphere.material)?;
+        let transformed = sphere.rotation.is_some() || sphere.scale.is_some();
+
+        let object = Box::new(Sphere {
+            center: if transformed { Vec3::zeros() } else { to_vec3(sphere.center) },
+            radius: sphere.radius,
+            material: Arc::clone(material),
+        });
+        objects.push(place(object, sphere.center, sphere.rotation, sphere.scale));
+    }
+
+    for ellipsoid in &file.ellipsoids {
+        let material = find_material(source, &materials, &ellipsoid.material)?;
+        let transformed = ellipsoid.rotation.is_some() || ellipsoid.scale.is_some();
+
+        let object = Box::new(Ellipsoid {
+            center: if transformed { Vec3::zeros() } else { to_vec3(ellipsoid.center) },
+            radii: to_vec3(ellipsoid.radii),
+            material: Arc::clone(material),
+        });
+        objects.push(place(object, ellipsoid.center, ellipsoid.rotation, ellipsoid.scale));
+    }
+
+    for section in &file.meshes {
+        let transform = compose(
+            to_vec3(section.translate),
+            to_vec3(section.rotation),
+            Vec3::new(section.scale, section.scale, section.scale),
+        );
+        let mut mesh = load_obj(&resolve(directory, section.path.get_ref()), &transform, &mut assets)
+            .map_err(|e| error_at(source, section.path.span().start, &e))?;
+
+        if let Some(name) = &section.material {
+            let material = find_material(source, &materials, name)?;
+            for slot in mesh.materials.iter_mut() {
+                *slot = Arc::clone(material);
+            }
+        }
+
+        objects.extend(mesh.into_triangles());
+    }
+
+    if let Some(section) = &file.voxels {
+        objects.push(Box::new(build_voxels(source, section, &materials)?));
+    }
+
+    let camera = Camera::new(
+        to_vec3(file.camera.eye),
+        to_vec3(file.camera.center),
+        to_vec3(file.camera.up),
+    );
+
+    let lights = file
+        .lights
+        .iter()
+        .map(|light| build_light(source, light))
+        .collect::<Result<Vec<Light>, String>>()?;
+
+    let orbit = file.orbit.as_ref().map(|orbit| Orbit {
+        center: to_vec3(orbit.center),
+        radius: orbit.radius,
+        phases: file
+            .lights
+            .iter()
+            .enumerate()
+            .filter_map(|(index, light)| light.get_ref().orbit_phase.map(|phase| (index, phase.to_radians())))
+            .collect(),
+    });
+
+    if file.render.width == Some(0) || file.render.height == Some(0) {
+        return Err(String::from("render: el ancho y el alto deben ser mayores que cero"));
+    }
+
+    let tone_mapping = match &file.render.tone_mapping {
+        Some(name) => parse_spanned(source, name)?,
+        None => ToneMapping::Aces,
+    };
+
+    let filter = match &file.render.filter {
+        Some(name) => parse_spanned(source, name)?,
+        None => Filter::Tent,
+    };
+
+    if file.render.samples == Some(0) {
+        return Err(String::from("render: samples debe ser mayor que cero"));
+    }
+    if file.render.shadow_samples == Some(0) {
+        return Err(String::from("render: shadow_samples debe ser mayor que cero"));
+    }
+
+    let settings = RenderSettings {
+        width: file.render.width.unwrap_or(800),
+        height: file.render.height.unwrap_or(600),
+        fov: file.render.fov.unwrap_or(60.0).to_radians(),
+        light_angle: file.render.light_angle,
+        threads: file.render.threads.unwrap_or(0),
+        exposure: file.render.exposure.unwrap_or(1.0),
+        tone_mapping,
+        samples: file.render.samples.unwrap_or(1),
+        filter,
+        shadow_samples: file.render.shadow_samples.unwrap_or(4),
+    };
+
+    let sky = match &file.sky {
+        Some(section) => Some(build_sky(source, section, &lights)?),
+        None => None,
+    };
+
+    let environment = match &file.environment {
+        Some(section) => {
+            if file.sky.is_some() {
+                return Err(error_at(source, section.span().start, "Se usa [sky] o [environment], no ambos"));
+            }
+            Some(build_environment(source, directory, section, &mut assets)?)
+        }
+        None => None,
+    };
+
+    let mut scene = Scene::new(objects, lights);
+    if let Some(sky) = sky {
+        scene.background = Background::Sky(sky);
+        scene.update_sky();
+    }
+    if let Some(environment) = environment {
+        scene.background = Background::Environment(environment);
+    }
+
+    Ok(LoadedScene { scene, camera, orbit, settings })
+}
+
+// Un objeto con rotación o escala se construye en el origen y se envuelve con su matriz
+fn place(object: Box<dyn RayIntersect>, center: [f32; 3], rotation: Option<[f32; 3]>, scale: Option<[f32; 3]>) -> Box<dyn RayIntersect> {
+    if rotation.is_none() && scale.is_none() {
+        return object;
+    }
+
+    let transform = compose(
+        to_vec3(center),
+        to_vec3(rotation.unwrap_or([0.0; 3])),
+        to_vec3(scale.unwrap_or([1.0; 3])),
+    );
+    Box::new(Transformed::new(object, transform))
+}
+
+fn find_material<'a>(
+    source: &str,
+    materials: &'a HashMap<&str, Arc<Material>>,
+    name: &Spanned<String>,
+) -> Result<&'a Arc<Material>, String> {
+    materials
+        .get(name.get_ref().as_str())
+        .ok_or_else(|| error_at(source, name.span().start, &format!("Material desconocido '{}'", name.get_ref())))
+}
+
+fn build_voxels(source: &str, section: &VoxelSection, materials: &HashMap<&str, Arc<Material>>) -> Result<VoxelGrid, String> {
+    let mut grid = VoxelGrid::new(to_vec3(section.origin), section.block_size, section.size);
+    let mut block_ids: HashMap<&str, u16> = HashMap::new();
+
+    for block in &section.blocks {
+        let name = block.material.get_ref().as_str();
+        let id = match block_ids.get(name) {
+            Some(&id) => id,
+            None => {
+                let id = grid
+                    .add_block_type(Arc::clone(find_material(source, materials, &block.material)?))
+                    .map_err(|e| error_at(source, block.material.span().start, &e))?;
+                block_ids.insert(name, id);
+                id
+            }
+        };
+
+        let min = *block.min.get_ref();
+        let max = block.max.unwrap_or(min);
+        let outside = (0..3).any(|axis| min[axis] > max[axis] || max[axis] >= section.size[axis]);
+        if outside {
+            return Err(error_at(source, block.min.span().start, "La región de bloques queda fuera de la rejilla"));
+        }
+
+        grid.fill(min, max, id);
+    }
+
+    Ok(grid)
+}
+
+fn build_material(source: &str, directory: &Path, section: &MaterialSection, assets: &mut AssetCache) -> Result<Material, String> {
+    let textures: [Option<Texture>; 6] = match (&section.texture, &section.faces) {
+        (Some(_), Some(faces)) => {
+            return Err(error_at(source, faces.span().start, "Se usa 'texture' o 'faces', no ambos"));
+        }
+        (Some(path), None) => {
+            let texture = load_texture(source, directory, assets, path, true)?;
+            std::array::from_fn(|_| Some(texture.clone()))
+        }
+        (None, Some(faces)) => {
+            if faces.get_ref().len() != 6 {
+                return Err(error_at(source, faces.span().start, "'faces' debe tener exactamente 6 texturas"));
+            }
+            let mut textures: [Option<Texture>; 6] = Default::default();
+            for (slot, path) in textures.iter_mut().zip(faces.get_ref()) {
+                *slot = Some(load_texture(source, directory, assets, path, true)?);
+            }
+            textures
+        }
+        (None, None) => Default::default(),
+    };
+
+    let normal_map = match &section.normal_map {
+        Some(path) => Some(load_texture(source, directory, assets, path, false)?),
+        None => None,
+    };
+
+    let mut material = match section.albedo {
+        Some(albedo) => Material::from_albedo(
+            section.diffuse.as_ref().map_or(Color::rgb(1.0, 1.0, 1.0), |c| to_color(*c.get_ref())),
+            section.specular.as_ref().map_or(0.0, |s| *s.get_ref()),
+            albedo,
+            section.refractive_index.as_ref().map_or(0.0, |r| *r.get_ref()),
+            textures,
+            normal_map,
+        ),
+        None => {
+            let legacy = [
+                section.diffuse.as_ref().map(|c| ("diffuse", c.span())),
+                section.specular.as_ref().map(|s| ("specular", s.span())),
+                section.refractive_index.as_ref().map(|r| ("refractive_index", r.span())),
+            ];
+            if let Some((key, span)) = legacy.into_iter().flatten().next() {
+                let message = format!("'{}' es del formato albedo; use base_color, roughness e ior", key);
+                return Err(error_at(source, span.start, &message));
+            }
+
+            // Sin color base, una textura se ve tal cual y una superficie lisa queda gris claro
+            let default_color = if textures.iter().any(Option::is_some) { [255, 255, 255] } else { [204, 204, 204] };
+            Material::new(to_color(default_color), 0.0, 0.5, textures, normal_map)
+        }
+    };
+
+    if let Some(color) = section.base_color {
+        material.base_color = to_color(color);
+    }
+    if let Some(metallic) = &section.metallic {
+        material.metallic = unit_interval(source, "metallic", metallic)?;
+    }
+    if let Some(roughness) = &section.roughness {
+        material.roughness = unit_interval(source, "roughness", roughness)?;
+    }
+    if let Some(transmission) = &section.transmission {
+        material.transmission = unit_interval(source, "transmission", transmission)?;
+    }
+    if let Some(ior) = &section.ior {
+        if *ior.get_ref() < 1.0 {
+            return Err(error_at(source, ior.span().start, "ior debe ser al menos 1"));
+        }
+        material.ior = *ior.get_ref();
+    }
+    if let Some(color) = section.emission {
+        material.emission = to_color(color) * section.emission_strength.unwrap_or(1.0);
+    }
+
+    if let Some(strength) = section.normal_strength {
+        material.normal_strength = strength;
+    }
+    if let Some(name) = &section.normal_convention {
+        material.normal_convention = parse_spanned::<NormalMapConvention>(source, name)?;
+    }
+    if let Some(name) = &section.texture_filter {
+        material.texture_filter = parse_spanned::<TextureFilter>(source, name)?;
+    }
+    if let Some(name) = &section.wrap {
+        material.wrap = parse_spanned::<WrapMode>(source, name)?;
+    }
+    if let Some(name) = &section.alpha_mode {
+        material.alpha_mode = parse_spanned::<AlphaMode>(source, name)?;
+    }
+    if let Some(cutoff) = section.alpha_cutoff {
+        material.alpha_cutoff = cutoff;
+    }
+
+    Ok(material)
+}
+
+fn build_light(source: &str, light: &Spanned<LightSection>) -> Result<Light, String> {
+    let section = light.get_ref();
+    let kind_name = section.kind.as_ref().map_or("point", |kind| kind.get_ref().as_str());
+    let missing = |key: &str| error_at(source, light.span().start, &format!("La luz {} necesita '{}'", kind_name, key));
+
+    let position = || section.position.map(to_vec3).ok_or_else(|| missing("position"));
+    let direction = || {
+        let direction = to_vec3(section.direction.ok_or_else(|| missing("direction"))?);
+        if direction.magnitude() <= 1e-6 {
+            return Err(error_at(source, light.span().start, "La dirección de la luz no puede ser cero"));
+        }
+        Ok(direction.normalize())
+    };
+
+    let radius = || {
+        let radius = section.radius.unwrap_or(0.0);
+        if radius < 0.0 {
+            return Err(error_at(source, light.span().start, "El radio de la luz no puede ser negativo"));
+        }
+        Ok(radius)
+    };
+
+    let kind = match kind_name {
+        "point" => LightKind::Point { position: position()?, radius: radius()? },
+        "directional" => {
+            let angle = section.angle.unwrap_or(0.0);
+            if !(0.0..45.0).contains(&angle) {
+                return Err(error_at(source, light.span().start, "El ángulo de la luz direccional debe estar entre 0 y 45 grados"));
+            }
+            LightKind::Directional { direction: direction()?, angle: angle.to_radians() }
+        }
+        "spot" => {
+            let angle = section.angle.ok_or_else(|| missing("angle"))?;
+            if angle <= 0.0 || angle >= 90.0 {
+                return Err(error_at(source, light.span().start, "El ángulo del foco debe estar entre 0 y 90 grados"));
+            }
+            LightKind::Spot {
+                position: position()?,
+                direction: direction()?,
+                angle: angle.to_radians(),
+                blend: section.blend.unwrap_or(0.15).clamp(0.0, 1.0),
+                radius: radius()?,
+            }
+        }
+        "rect" => {
+            let size = section.size.ok_or_else(|| missing("size"))?;
+            if size[0] <= 0.0 || size[1] <= 0.0 {
+                return Err(error_at(source, light.span().start, "Los lados de una luz rect deben ser mayores que cero"));
+            }
+            let direction = direction()?;
+            let (axis_u, axis_v) = orthonormal_basis(&direction);
+            LightKind::Rect {
+                position: position()?,
+                direction,
+                half_u: axis_u * (size[0] * 0.5),
+                half_v: axis_v * (size[1] * 0.5),
+            }
+        }
+        "disk" => {
+            let radius = section.radius.ok_or_else(|| missing("radius"))?;
+            if radius <= 0.0 {
+                return Err(error_at(source, light.span().start, "El radio de una luz disk debe ser mayor que cero"));
+            }
+            LightKind::Disk { position: position()?, direction: direction()?, radius }
+        }
+        other => {
+            let span = section.kind.as_ref().map_or(light.span(), |kind| kind.span());
+            let message = format!("Tipo de luz desconocido '{}' (se espera point, directional, spot, rect o disk)", other);
+            return Err(error_at(source, span.start, &message));
+        }
+    };
+
+    let mut built = Light::new(kind, to_color(section.color), section.intensity);
+    if let Some(name) = &section.falloff {
+        if kind_name == "directional" {
+            return Err(error_at(source, name.span().start, "Las luces direccionales no se atenúan"));
+        }
+        built.falloff = match name.get_ref().as_str() {
+            "none" => Falloff::None,
+            "inverse_square" => Falloff::InverseSquare,
+            "linear" => Falloff::Linear,
+            "range" => {
+                let range = section.range.ok_or_else(|| missing("range"))?;
+                if range <= 0.0 {
+                    return Err(error_at(source, light.span().start, "El alcance de la luz debe ser mayor que cero"));
+                }
+                Falloff::Range { range }
+            }
+            other => {
+                let message = format!("Atenuación desconocida '{}' (se espera none, inverse_square, linear o range)", other);
+                return Err(error_at(source, name.span().start, &message));
+            }
+        };
+    }
+    if section.range.is_some() && !matches!(built.falloff, Falloff::Range { .. }) {
+        return Err(error_at(source, light.span().start, "'range' requiere falloff = \"range\""));
+    }
+
+    Ok(built)
+}
+
+fn build_sky(source: &str, sky: &Spanned<SkySection>, lights: &[Light]) -> Result<Sky, String> {
+    let section = sky.get_ref();
+
+    let sun = match &section.sun {
+        Some(index) => {
+            if *index.get_ref() >= lights.len() {
+                let message = format!("El sol del cielo {} está fuera de rango ({} luces)", index.get_ref(), lights.len());
+                return Err(error_at(source, index.span().start, &message));
+            }
+            *index.get_ref()
+        }
+        None => lights
+            .iter()
+            .position(|light| matches!(light.kind, LightKind::Directional { .. }))
+            .ok_or_else(|| error_at(source, sky.span().start, "El cielo necesita una luz direccional o 'sun'"))?,
+    };
+
+    let turbidity = match &section.turbidity {
+        Some(turbidity) => {
+            if !(1.7..=10.0).contains(turbidity.get_ref()) {
+                return Err(error_at(source, turbidity.span().start, "La turbidez del cielo debe estar entre 1.7 y 10"));
+            }
+            *turbidity.get_ref()
+        }
+        None => 3.0,
+    };
+
+    Ok(Sky::new(sun, turbidity, section.intensity.unwrap_or(1.0)))
+}
+
+fn build_environment(source: &str, directory: &Path, environment: &Spanned<EnvironmentSection>, assets: &mut AssetCache) -> Result<EnvironmentMap, String> {
+    let section = environment.get_ref();
+
+    let image = match (&section.path, &section.faces) {
+        (Some(_), Some(faces)) => {
+            return Err(error_at(source, faces.span().start, "Se usa 'path' o 'faces', no ambos"));
+        }
+        (Some(path), None) => EnvironmentImage::Equirectangular(load_texture(source, directory, assets, path, true)?),
+        (None, Some(faces)) => {
+            let [px, nx, py, ny, pz, nz] = faces.get_ref().as_slice() else {
+                return Err(error_at(source, faces.span().start, "'faces' debe tener exactamente 6 imágenes"));
+            };
+            let mut load = |path| load_texture(source, directory, assets, path, true);
+            EnvironmentImage::Cubemap(Box::new([load(px)?, load(nx)?, load(py)?, load(ny)?, load(pz)?, load(nz)?]))
+        }
+        (None, None) => {
+            return Err(error_at(source, environment.span().start, "El entorno necesita 'path' o 'faces'"));
+        }
+    };
+
+    Ok(EnvironmentMap {
+        image,
+        rotation: section.rotation.to_radians(),
+        intensity: section.intensity.unwrap_or(1.0),
+    })
+}
+
+fn unit_interval(source: &str, key: &str, value: &Spanned<f32>) -> Result<f32, String> {
+    let number = *value.get_ref();
+    if !(0.0..=1.0).contains(&number) {
+        return Err(error_at(source, value.span().start, &format!("{} debe estar entre 0 y 1", key)));
+    }
+    Ok(number)
+}
+
+// Valor de texto que se convierte con FromStr; el error apunta a su línea
+fn parse_spanned<T: std::str::FromStr<Err = String>>(source: &str, value: &Spanned<String>) -> Result<T, String> {
+    value.get_ref().parse().map_err(|e: String| error_at(source, value.span().start, &e))
+}
+
+fn load_texture(source: &str, directory: &Path, assets: &mut AssetCache, path: &Spanned<String>, srgb: bool) -> Result<Texture, String> {
+    assets
+        .texture(&resolve(directory, path.get_ref()), srgb)
+        .map_err(|e| error_at(source, path.span().start, &e))
+}
+
+// Una ruta absoluta queda igual; una relativa se toma desde la carpeta de la escena
+fn resolve(directory: &Path, path: &str) -> String {
+    directory.join(path).to_string_lossy().into_owned()
+}
+
+fn to_vec3(v: [f32; 3]) -> Vec3 {
+    Vec3::new(v[0], v[1], v[2])
+}
+
+fn to_color(c: [u8; 3]) -> Color {
+    Color::new(c[0], c[1], c[2])
+}
+
+fn line_of(source: &str, offset: usize) -> usize {
+    source[..offset.min(source.len())].matches('\n').count() + 1
+}
+
+fn error_at(source: &str, offset: usize, message: &str) -> String {
+    format!("línea {}: {}", line_of(source, offset), message)
+}
+
+fn format_toml_error(source: &str, error: &toml::de::Error) -> String {
+    match error.span() {
+        Some(span) => error_at(source, span.start, error.message()),
+        None => error.message().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::parse_scene;
+
+    fn parse_error(source: &str) -> String {
+        parse_scene(source, Path::new("")).err().expect("la escena debía fallar")
+    }
+
+    #[test]
+    fn malformed_toml_reports_its_line() {
+        let syntax = "[camera]\neye = [0.0, 0.0, 10.0]\ncenter = = [0.0, 0.0, 0.0]\n";
+        let error = parse_error(syntax);
+        assert!(error.starts_with("línea 3: "), "{}", error);
+
+        let wrong_type = "[camera]\neye = [0.0, 0.0, 10.0]\ncenter = \"origen\"\n";
+        let error = parse_error(wrong_type);
+        assert!(error.starts_with("línea 3: "), "{}", error);
+    }
+
+    #[test]
+    fn unknown_material_reports_the_line_of_the_reference() {
+        let source = "\
+[camera]
+eye = [0.0, 0.0, 10.0]
+center = [0.0, 0.0, 0.0]
+
+[materials.piedra]
+base_color = [128, 128, 128]
+
+[[cubes]]
+material = \"madera\"
+center = [0.0, 0.0, 0.0]
+half_size = [1.0, 1.0, 1.0]
+";
+        let error = parse_error(source);
+        assert_eq!(error, "línea 9: Material desconocido 'madera'");
+    }
+
+    #[test]
+    fn degenerate_area_lights_are_rejected() {
+        let camera = "[camera]\neye = [0.0, 0.0, 10.0]\ncenter = [0.0, 0.0, 0.0]\n\n";
+        let rect = "[[lights]]\ntype = \"rect\"\nposition = [0.0, 5.0, 0.0]\ndirection = [0.0, -1.0, 0.0]\nsize = [2.0, 0.0]\ncolor = [255, 255, 255]\nintensity = 1.0\n";
+        let disk = "[[lights]]\ntype = \"disk\"\nposition = [0.0, 5.0, 0.0]\ndirection = [0.0, -1.0, 0.0]\nradius = -1.0\ncolor = [255, 255, 255]\nintensity = 1.0\n";
+
+        for light in [rect, disk] {
+            let error = parse_error(&format!("{}{}", camera, light));
+            assert!(error.starts_with("línea 5: "), "{}", error);
+            assert!(error.ends_with("que cero"), "{}", error);
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use nalgebra_glm::Vec3;
+use std::f32::consts::PI;
+
+use crate::color::Color;
+
+// Lleva la luminancia del modelo (kcd/m²) a las unidades del render: el cenit a mediodía
+// queda parecido al color fijo que había antes
+const LUMINANCE_SCALE: f32 = 0.025;
+
+// Brillo del disco del sol respecto de la intensidad de su luz, antes del tone mapping
+const SUN_DISK_SCALE: f32 = 20.0;
+
+// Cielo de noche, cuando el sol ya se puso
+const NIGHT_COLOR: Color = Color::rgb(0.002, 0.004, 0.012);
+
+// Radio aparente del sol real, para cuando la luz no define el suyo
+pub const DEFAULT_SUN_ANGLE: f32 = 0.0047;
+
+// Los cinco coeficientes de la función de Perez para una magnitud (Y, x o y)
+#[derive(Clone, Copy)]
+struct Perez {
+    a: f32,
+    b: f32,
+    c: f32,
+    d: f32,
+    e: f32,
+}
+
+impl Perez {
+    // theta es el ángulo desde el cenit; gamma, el ángulo con el sol
+    fn at(&self, cos_theta: f32, gamma: f32, cos_gamma: f32) -> f32 {
+        (1.0 + self.a * (self.b / cos_theta).exp())
+            * (1.0 + self.c * (self.d * gamma).exp() + self.e * cos_gamma * cos_gamma)
+    }
+}
+
+// Cielo analítico de Preetham (1999). Depende de la turbidez y de la dirección del sol, que
+// se actualiza cuando la luz se mueve en su órbita.
+#[derive(Clone)]
+pub struct Sky {
+    pub sun_light: usize,     // Índice de la luz que hace de sol
+    pub turbidity: f32,       // 2 es un día muy limpio, 10 uno brumoso
+    pub intensity: f32,
+    sun_direction: Vec3,      // Hacia el sol, unitaria
+    sun_angle: f32,           // Radio aparente del disco, en radianes
+    sun_color: Color,         // Color e intensidad de la luz, antes de atravesar el aire
+    daylight: f32,            // 1 de día; baja a 0 cuando el sol se pone
+    luminance: Perez,
+    chroma_x: Perez,
+    chroma_y: Perez,
+    zenith: (f32, f32, f32),  // (Y, x, y) en el cenit
+    normalization: (f32, f32, f32),  // Perez de cada magnitud en el cenit
+    sun_transmittance: Color,
+}
+
+impl Sky {
+    pub fn new(sun_light: usize, turbidity: f32, intensity: f32) -> Self {
+        let mut sky = Sky {
+            sun_light,
+            turbidity,
+            intensity,
+            sun_direction: Vec3::y(),
+            sun_angle: DEFAULT_SUN_ANGLE,
+            sun_color: Color::black(),
+            daylight: 1.0,
+            luminance: Perez { a: 0.0, b: 0.0, c: 0.0, d: 0.0, e: 0.0 },
+            chroma_x: Perez { a: 0.0, b: 0.0, c: 0.0, d: 0.0, e: 0.0 },
+            chroma_y: Perez { a: 0.0, b: 0.0, c: 0.0, d: 0.0, e: 0.0 },
+            zenith: (0.0, 0.0, 0.0),
+            normalization: (1.0, 1.0, 1.0),
+            sun_transmittance: Color::black(),
+        };
+        sky.set_sun(Vec3::y(), DEFAULT_SUN_ANGLE, Color::black());
+        sky
+    }
+
+    // Recalcula el modelo para un sol en esa dirección (hacia el sol), con ese radio aparente
+    // y el color ya multiplicado por la intensidad de la luz
+    pub fn set_sun(&mut self, direction: Vec3, angle: f32, color: Color) {
+        let t = self.turbidity;
+        self.sun_direction = direction.normalize();
+        self.sun_angle = angle;
+        self.sun_color = color;
+
+        // El modelo no vale con el sol bajo el horizonte: se lo deja apenas sobre él y se
+        // oscurece el cielo hasta la noche
+        let elevation = self.sun_direction.y.asin();
+        self.daylight = smoothstep(-0.1, 0.05, elevation);
+        let theta_s = (PI / 2.0 - elevation).min(PI / 2.0 - 0.01);
+
+        self.luminance = Perez {
+            a: 0.1787 * t - 1.4630,
+            b: -0.3554 * t + 0.4275,
+            c: -0.0227 * t + 5.3251,
+            d: 0.1206 * t - 2.5771,
+            e: -0.0670 * t + 0.3703,
+        };
+        self.chroma_x = Perez {
+            a: -0.0193 * t - 0.2592,
+            b: -0.0665 * t + 0.0008,
+            c: -0.0004 * t + 0.2125,
+            d: -0.0641 * t - 0.8989,
+            e: -0.0033 * t + 0.0452,
+        };
+        self.chroma_y = Perez {
+            a: -0.0167 * t - 0.2608,
+            b: -0.0950 * t + 0.0092,
+            c: -0.0079 * t + 0.2102,
+            d: -0.0441 * t - 1.6537,
+            e: -0.0109 * t + 0.0529,
+        };
+
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_s);
+        let zenith_luminance = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+        let (s1, s2, s3) = (theta_s, theta_s * theta_s, theta_s * theta_s * theta_s);
+        let zenith_x = t * t * (0.00166 * s3 - 0.00375 * s2 + 0.00209 * s1)
+            + t * (-0.02903 * s3 + 0.06377 * s2 - 0.03202 * s1 + 0.00394)
+            + (0.11693 * s3 - 0.21196 * s2 + 0.06052 * s1 + 0.25886);
+        let zenith_y = t * t * (0.00275 * s3 - 0.00610 * s2 + 0.00317 * s1)
+            + t * (-0.04214 * s3 + 0.08970 * s2 - 0.04153 * s1 + 0.00516)
+            + (0.15346 * s3 - 0.26756 * s2 + 0.06670 * s1 + 0.26688);
+        self.zenith = (zenith_luminance.max(0.0), zenith_x, zenith_y);
+
+        let cos_s = theta_s.cos();
+        self.normalization = (
+            self.luminance.at(1.0, theta_s, cos_s),
+            self.chroma_x.at(1.0, theta_s, cos_s),
+            self.chroma_y.at(1.0, theta_s, cos_s),
+        );
+
+        self.sun_transmittance = atmosphere_transmittance(theta_s, t);
+    }
+
+    // Radiancia del cielo en esa dirección (unitaria, saliendo de la escena)
+    pub fn radiance(&self, direction: &Vec3) -> Color {
+        // Debajo del horizonte se refleja el cielo: las escenas flotan, sin suelo hasta el horizonte
+        let cos_theta = direction.y.abs() + 0.01;
+        let cos_gamma = direction.dot(&self.sun_direction).clamp(-1.0, 1.0);
+        let gamma = cos_gamma.acos();
+
+        let (zenith_luminance, zenith_x, zenith_y) = self.zenith;
+        let (norm_luminance, norm_x, norm_y) = self.normalization;
+        let luminance = zenith_luminance * self.luminance.at(cos_theta, gamma, cos_gamma) / norm_luminance;
+        let x = zenith_x * self.chroma_x.at(cos_theta, gamma, cos_gamma) / norm_x;
+        let y = zenith_y * self.chroma_y.at(cos_theta, gamma, cos_gamma) / norm_y;
+
+        let sky = xyy_to_rgb(x, y, luminance * LUMINANCE_SCALE) * self.daylight;
+        let mut color = sky + NIGHT_COLOR * (1.0 - self.daylight);
+
+        // Disco del sol, enrojecido por el aire que atraviesa cerca del horizonte
+        if direction.y > 0.0 && gamma < self.sun_angle {
+            color = color + self.sun_color * self.sun_transmittance * (SUN_DISK_SCALE * self.daylight);
+        }
+
+        color * self.intensity
+    }
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+// Cromaticidad xy y luminancia Y a RGB lineal (primarios sRGB)
+fn xyy_to_rgb(x: f32, y: f32, luminance: f32) -> Color {
+    if y <= 0.0 {
+        return Color::black();
+    }
+    let big_x = x * luminance / y;
+    let big_z = (1.0 - x - y) * luminance / y;
+    Color::rgb(
+        (3.2406 * big_x - 1.5372 * luminance - 0.4986 * big_z).max(0.0),
+        (-0.9689 * big_x + 1.8758 * luminance + 0.0415 * big_z).max(0.0),
+        (0.0557 * big_x - 0.2040 * luminance + 1.0570 * big_z).max(0.0),
+    )
+}
+
+// Fracción de la luz del sol que atraviesa la atmósfera con el sol a theta_s del cenit:
+// dispersión de Rayleigh y de aerosoles (según la turbidez) en tres longitudes de onda,
+// con la masa de aire de Kasten y Young
+fn atmosphere_transmittance(theta_s: f32, turbidity: f32) -> Color {
+    let degrees = theta_s.to_degrees();
+    let air_mass = 1.0 / (theta_s.cos() + 0.50572 * (96.07995 - degrees).powf(-1.6364));
+    let beta = 0.04608 * turbidity - 0.04586;
+
+    let channel = |wavelength: f32| {
+        let rayleigh = 0.008569 * wavelength.powi(-4);
+        let aerosol = beta * wavelength.powf(-1.3);
+        (-(rayleigh + aerosol) * air_mass).exp()
+    };
+    Color::rgb(channel(0.68), channel(0.55), channel(0.44))
+}
@@ -0,0 +1,154 @@
+use nalgebra_glm::{Vec2, Vec3};
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::ray_intersect::{Intersect, RayIntersect};
+
+// Las esferas y elipsoides tienen una sola superficie: usan la textura de la cara 0 del material
+const SURFACE_INDEX: usize = 0;
+
+pub struct Sphere {
+    pub center: Vec3,
+    pub radius: f32,
+    pub material: Arc<Material>,
+}
+
+// Esfera escalada en cada eje; radii son los semiejes en x, y, z
+pub struct Ellipsoid {
+    pub center: Vec3,
+    pub radii: Vec3,
+    pub material: Arc<Material>,
+}
+
+// Mapeo equirectangular de un punto sobre la esfera unitaria: u da la vuelta, v va del polo norte al sur
+fn sphere_uv(local: &Vec3) -> (f32, f32) {
+    let u = 0.5 + local.z.atan2(local.x) / (2.0 * PI);
+    let v = local.y.clamp(-1.0, 1.0).acos() / PI;
+    (u, v)
+}
+
+// Derivadas de un punto de la esfera unitaria respecto de u (alrededor del eje y) y de v
+// (del polo norte hacia el sur), sin normalizar. En los polos la de v se anula.
+fn sphere_uv_axes(local: &Vec3) -> (Vec3, Vec3) {
+    let tangent = Vec3::new(-local.z, 0.0, local.x);
+    let bitangent = Vec3::new(local.x * local.y, -(local.x * local.x + local.z * local.z), local.z * local.y);
+    (tangent, bitangent)
+}
+
+// Raíces de |o + t d|^2 = 1 (entrada y salida), si el rayo corta la esfera unitaria
+fn unit_sphere_roots(origin: &Vec3, direction: &Vec3) -> Option<(f32, f32)> {
+    let a = direction.dot(direction);
+    let b = origin.dot(direction);
+    let c = origin.dot(origin) - 1.0;
+    let discriminant = b * b - a * c;
+
+    if discriminant < 0.0 {
+        return None;
+    }
+
+    let root = discriminant.sqrt();
+    Some(((-b - root) / a, (-b + root) / a))
+}
+
+// Tamaño en el mundo de una unidad de UV: media geométrica del ecuador (2πr) y el meridiano (πr)
+fn world_per_uv(radius: f32) -> f32 {
+    PI * std::f32::consts::SQRT_2 * radius
+}
+
+// Intersección con el elipsoide de semiejes radii (una esfera si son iguales). El rayo se lleva
+// al espacio donde es la esfera unitaria; t no cambia. Si el origen está adentro se usa la
+// salida; si la entrada cae en un texel transparente, se ve el interior de la salida.
+fn ellipsoid_intersect<'a>(
+    center: &Vec3,
+    radii: &Vec3,
+    material: &'a Material,
+    ray: &Ray,
+) -> Intersect<'a> {
+    let origin = (ray.origin - center).component_div(radii);
+    let direction = ray.direction.component_div(radii);
+
+    let Some((t_near, t_far)) = unit_sphere_roots(&origin, &direction) else {
+        return Intersect::empty();
+    };
+
+    let mean_radius = (radii.x + radii.y + radii.z) / 3.0;
+
+    for (distance, facing) in [(t_near, 1.0), (t_far, if t_near > 0.0 { -1.0 } else { 1.0 })] {
+        if distance <= ray.t_min {
+            continue;
+        }
+        // t_far viene después de t_near: si esta ya pasó el final del rayo, no hay más
+        if distance > ray.t_max {
+            break;
+        }
+
+        let point = ray.at(distance);
+        let local = (point - center).component_div(radii);
+        // La normal de la superficie escalada es el gradiente: local / radii
+        let normal = local.component_div(radii).normalize() * facing;
+        if ray.is_back_face(&normal) {
+            continue;
+        }
+        let footprint = ray.cone.uv_footprint(distance, &ray.direction, &normal, world_per_uv(mean_radius));
+        let (u, v) = sphere_uv(&local);
+
+        let hit = Intersect::surface(material, point, normal, distance, SURFACE_INDEX, Vec2::new(u, v), footprint);
+        if let Some(hit) = hit {
+            // Al volver al elipsoide las derivadas se estiran con los semiejes
+            let (tangent, bitangent) = sphere_uv_axes(&local);
+            return hit.with_tangents(tangent.component_mul(radii), bitangent.component_mul(radii));
+        }
+    }
+
+    Intersect::empty()
+}
+
+impl RayIntersect for Sphere {
+    fn ray_intersect(&self, ray: &Ray) -> Intersect<'_> {
+        let radii = Vec3::new(self.radius, self.radius, self.radius);
+        ellipsoid_intersect(&self.center, &radii, &self.material, ray)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let half = Vec3::new(self.radius, self.radius, self.radius);
+        Aabb::new(self.center - half, self.center + half)
+    }
+}
+
+impl RayIntersect for Ellipsoid {
+    fn ray_intersect(&self, ray: &Ray) -> Intersect<'_> {
+        ellipsoid_intersect(&self.center, &self.radii, &self.material, ray)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        Aabb::new(self.center - self.radii, self.center + self.radii)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra_glm::Vec3;
+    use std::sync::Arc;
+
+    use super::Sphere;
+    use crate::material::Material;
+    use crate::ray::{Ray, RayCone, RayKind};
+    use crate::ray_intersect::RayIntersect;
+
+    #[test]
+    fn ignores_hits_past_t_max() {
+        let sphere = Sphere { center: Vec3::zeros(), radius: 1.0, material: Arc::new(Material::black()) };
+        let shadow = Ray::new(Vec3::new(0.0, 0.0, -5.0), Vec3::z(), RayKind::Shadow, RayCone::new(0.0, 0.0));
+
+        assert!(!sphere.ray_intersect(&shadow.with_max_distance(3.0)).is_intersecting);
+        let hit = sphere.ray_intersect(&shadow.with_max_distance(10.0));
+        assert!(hit.is_intersecting && (hit.distance - 4.0).abs() < 1e-5);
+
+        let inside = Ray { origin: Vec3::zeros(), ..shadow };
+        assert!(!sphere.ray_intersect(&inside.with_max_distance(0.5)).is_intersecting);
+        assert!(sphere.ray_intersect(&inside.with_max_distance(1.5)).is_intersecting);
+    }
+}
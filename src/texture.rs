@@ -1,38 +1,280 @@
 extern crate image;
 
-use image::{DynamicImage, GenericImageView, Pixel, RgbaImage};
+use image::RgbaImage;
+use std::str::FromStr;
+use std::sync::Arc;
 
-#[derive(Debug, Clone)]
+use crate::color::srgb_to_linear;
+
+// Cómo se filtra la textura al muestrearla
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextureFilter {
+    Nearest,    // Texel más cercano del nivel 0, sin mipmaps
+    Bilinear,   // Interpolación bilineal en el nivel de mipmap más cercano
+    Trilinear,  // Bilineal en los dos niveles vecinos y mezcla entre ellos
+}
+
+// Qué pasa con las UV fuera de [0, 1]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WrapMode {
+    Repeat,
+    Mirror,
+    Clamp,
+}
+
+impl FromStr for TextureFilter {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, String> {
+        match name {
+            "nearest" => Ok(TextureFilter::Nearest),
+            "bilinear" => Ok(TextureFilter::Bilinear),
+            "trilinear" => Ok(TextureFilter::Trilinear),
+            _ => Err(format!("Filtro de textura desconocido '{}' (se espera nearest, bilinear o trilinear)", name)),
+        }
+    }
+}
+
+impl FromStr for WrapMode {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, String> {
+        match name {
+            "repeat" => Ok(WrapMode::Repeat),
+            "mirror" => Ok(WrapMode::Mirror),
+            "clamp" => Ok(WrapMode::Clamp),
+            _ => Err(format!("Modo de repetición desconocido '{}' (se espera repeat, mirror o clamp)", name)),
+        }
+    }
+}
+
+impl WrapMode {
+    // Lleva un índice de texel posiblemente fuera de rango a [0, size)
+    fn wrap(self, index: i64, size: u32) -> u32 {
+        let size = size as i64;
+        let wrapped = match self {
+            WrapMode::Repeat => index.rem_euclid(size),
+            WrapMode::Mirror => {
+                let period = index.rem_euclid(2 * size);
+                if period < size { period } else { 2 * size - 1 - period }
+            }
+            WrapMode::Clamp => index.clamp(0, size - 1),
+        };
+        wrapped as u32
+    }
+}
+
+// Rectángulo de texels dentro de un nivel de mipmap
+#[derive(Debug, Clone, Copy)]
+struct Rect {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+// Un nivel de la cadena de mipmaps, en RGBA de punto flotante
+#[derive(Debug)]
+struct MipLevel {
+    width: u32,
+    height: u32,
+    texels: Vec<[f32; 4]>,
+}
+
+impl MipLevel {
+    fn texel(&self, x: u32, y: u32) -> [f32; 4] {
+        self.texels[(y * self.width + x) as usize]
+    }
+
+    // Siguiente nivel a la mitad de tamaño, promediando bloques de 2x2
+    fn downsample(&self) -> MipLevel {
+        let width = (self.width / 2).max(1);
+        let height = (self.height / 2).max(1);
+        let mut texels = Vec::with_capacity((width * height) as usize);
+
+        for y in 0..height {
+            for x in 0..width {
+                let mut sum = [0.0; 4];
+                for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                    let texel = self.texel((2 * x + dx).min(self.width - 1), (2 * y + dy).min(self.height - 1));
+                    for channel in 0..4 {
+                        sum[channel] += texel[channel] * 0.25;
+                    }
+                }
+                texels.push(sum);
+            }
+        }
+
+        MipLevel { width, height, texels }
+    }
+
+    // Las coordenadas y el modo de repetición se aplican dentro de rect, así una región
+    // de un atlas se comporta como una textura independiente
+    fn nearest(&self, rect: Rect, u: f32, v: f32, wrap: WrapMode) -> [f32; 4] {
+        let x = wrap.wrap((u * rect.width as f32).floor() as i64, rect.width);
+        let y = wrap.wrap((v * rect.height as f32).floor() as i64, rect.height);
+        self.texel(rect.x + x, rect.y + y)
+    }
+
+    fn bilinear(&self, rect: Rect, u: f32, v: f32, wrap: WrapMode) -> [f32; 4] {
+        // Los centros de los texels están en (i + 0.5) / tamaño
+        let x = u * rect.width as f32 - 0.5;
+        let y = v * rect.height as f32 - 0.5;
+        let x0 = x.floor();
+        let y0 = y.floor();
+        let fx = x - x0;
+        let fy = y - y0;
 
+        let xs = [wrap.wrap(x0 as i64, rect.width), wrap.wrap(x0 as i64 + 1, rect.width)];
+        let ys = [wrap.wrap(y0 as i64, rect.height), wrap.wrap(y0 as i64 + 1, rect.height)];
+        let weights = [(1.0 - fx) * (1.0 - fy), fx * (1.0 - fy), (1.0 - fx) * fy, fx * fy];
+
+        let mut result = [0.0; 4];
+        for (i, weight) in weights.iter().enumerate() {
+            let texel = self.texel(rect.x + xs[i % 2], rect.y + ys[i / 2]);
+            for channel in 0..4 {
+                result[channel] += texel[channel] * weight;
+            }
+        }
+        result
+    }
+}
+
+// Cadena de mipmaps de una imagen, compartida por todas las texturas que la usan
+#[derive(Debug)]
+pub struct MipChain {
+    levels: Vec<MipLevel>,  // Nivel 0 a tamaño completo y luego cada mitad hasta 1x1
+}
+
+impl MipChain {
+    pub fn from_image(image: &RgbaImage, srgb: bool) -> Self {
+        let (width, height) = image.dimensions();
+        let decode = |c: u8| {
+            let c = c as f32 / 255.0;
+            if srgb { srgb_to_linear(c) } else { c }
+        };
+
+        let texels = image
+            .pixels()
+            .map(|p| [decode(p[0]), decode(p[1]), decode(p[2]), p[3] as f32 / 255.0])
+            .collect();
+
+        // Los mipmaps se promedian en espacio lineal
+        let mut levels = vec![MipLevel { width, height, texels }];
+        while let Some(last) = levels.last().filter(|level| level.width > 1 || level.height > 1) {
+            let next = last.downsample();
+            levels.push(next);
+        }
+
+        MipChain { levels }
+    }
+}
+
+// Handle barato de clonar: una región de una cadena de mipmaps compartida. Una textura
+// suelta ocupa toda la imagen; una del atlas, solo su celda.
+#[derive(Debug, Clone)]
 pub struct Texture {
     pub width: u32,
     pub height: u32,
-    pub data: RgbaImage,  // Almacena la imagen en formato RGBA
+    chain: Arc<MipChain>,
+    x: u32,
+    y: u32,
+    mip_count: usize,  // Niveles que no mezclan texels de fuera de la región
 }
 
 impl Texture {
-    // Cargar la textura desde un archivo
-    pub fn from_file(path: &str) -> Result<Self, String> {
-        match image::open(path) {
-            Ok(img) => {
-                let rgba_img = img.to_rgba8();  // Convierte la imagen a RGBA
-                let (width, height) = rgba_img.dimensions();
-
-                Ok(Texture {
-                    width,
-                    height,
-                    data: rgba_img,
-                })
+    pub fn from_image(image: &RgbaImage, srgb: bool) -> Self {
+        let chain = MipChain::from_image(image, srgb);
+        let mip_count = chain.levels.len();
+        Texture {
+            width: image.width(),
+            height: image.height(),
+            chain: Arc::new(chain),
+            x: 0,
+            y: 0,
+            mip_count,
+        }
+    }
+
+    // Región de width x height en (x, y) del nivel 0. Para que los mipmaps no mezclen
+    // regiones vecinas, (x, y) debe ser múltiplo del tamaño y este una potencia de dos.
+    pub fn region(chain: &Arc<MipChain>, x: u32, y: u32, width: u32, height: u32) -> Self {
+        Texture {
+            width,
+            height,
+            chain: Arc::clone(chain),
+            x,
+            y,
+            mip_count: width.min(height).ilog2() as usize + 1,
+        }
+    }
+
+    fn rect(&self, level: usize) -> Rect {
+        Rect {
+            x: self.x >> level,
+            y: self.y >> level,
+            width: (self.width >> level).max(1),
+            height: (self.height >> level).max(1),
+        }
+    }
+
+    // Muestra la textura en (u, v). footprint es el ancho del haz del rayo en unidades UV
+    // y decide el nivel de mipmap para los filtros que los usan.
+    pub fn sample(&self, u: f32, v: f32, footprint: f32, filter: TextureFilter, wrap: WrapMode) -> [f32; 4] {
+        let levels = &self.chain.levels;
+
+        let lod = (footprint * self.width.max(self.height) as f32).max(1e-8).log2();
+        let max_level = (self.mip_count - 1) as f32;
+        let lod = lod.clamp(0.0, max_level);
+
+        match filter {
+            TextureFilter::Nearest => levels[0].nearest(self.rect(0), u, v, wrap),
+            TextureFilter::Bilinear => {
+                let level = lod.round() as usize;
+                levels[level].bilinear(self.rect(level), u, v, wrap)
+            }
+            TextureFilter::Trilinear => {
+                let level = lod.floor() as usize;
+                let t = lod - level as f32;
+                let near = levels[level].bilinear(self.rect(level), u, v, wrap);
+                if t <= 0.0 {
+                    return near;
+                }
+
+                let far = levels[level + 1].bilinear(self.rect(level + 1), u, v, wrap);
+                std::array::from_fn(|channel| near[channel] * (1.0 - t) + far[channel] * t)
             }
-            Err(e) => Err(format!("Error loading texture: {}", e)),
         }
     }
+}
+
+// Escala de las pendientes: subir 0.25 de un texel al siguiente inclina la normal 45°
+const HEIGHT_MAP_SCALE: f32 = 4.0;
+
+// Convierte un mapa de alturas (el brillo de cada texel) en un mapa de normales en espacio
+// tangente, con el verde hacia arriba en la imagen como en OpenGL. Las diferencias se toman
+// con los texels vecinos, dando la vuelta en los bordes porque estas texturas suelen repetirse.
+pub fn height_to_normal_map(image: &RgbaImage) -> RgbaImage {
+    let (width, height) = image.dimensions();
+    let height_at = |x: i64, y: i64| {
+        let texel = image.get_pixel(x.rem_euclid(width as i64) as u32, y.rem_euclid(height as i64) as u32);
+        (texel[0] as f32 + texel[1] as f32 + texel[2] as f32) / (3.0 * 255.0)
+    };
+
+    RgbaImage::from_fn(width, height, |x, y| {
+        let (x, y) = (x as i64, y as i64);
+        let slope_x = (height_at(x + 1, y) - height_at(x - 1, y)) * 0.5 * HEIGHT_MAP_SCALE;
+        // Las filas crecen hacia abajo; la pendiente se mide hacia arriba
+        let slope_y = (height_at(x, y - 1) - height_at(x, y + 1)) * 0.5 * HEIGHT_MAP_SCALE;
+        let length = (slope_x * slope_x + slope_y * slope_y + 1.0).sqrt();
+        let encode = |c: f32| ((c / length * 0.5 + 0.5) * 255.0).round() as u8;
+        image::Rgba([encode(-slope_x), encode(-slope_y), encode(1.0), 255])
+    })
+}
 
-    // Obtener el color de un píxel (como un array de 4 valores RGBA)
-    pub fn get_pixel(&self, x: u32, y: u32) -> [u8; 4] {
-        let pixel = self.data.get_pixel(x, y);
-        let channels = pixel.channels();  // Obtén los canales como una referencia al array
-        [channels[0], channels[1], channels[2], channels[3]]  // Convierte la referencia en un array
+pub fn load_image(path: &str) -> Result<RgbaImage, String> {
+    match image::open(path) {
+        Ok(img) => Ok(img.to_rgba8()),
+        Err(e) => Err(format!("Error al cargar la textura: {}", e)),
     }
-    
 }
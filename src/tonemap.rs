@@ -0,0 +1,47 @@
+use std::str::FromStr;
+
+use crate::color::Color;
+
+// Operador que lleva la radiancia HDR al rango [0, 1] antes de codificar a sRGB
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ToneMapping {
+    Clamp,     // Recorta en 1, como antes del pipeline HDR
+    Reinhard,
+    Aces,      // Aproximación filmic de ACES (Narkowicz)
+}
+
+impl FromStr for ToneMapping {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, String> {
+        match name {
+            "clamp" | "none" => Ok(ToneMapping::Clamp),
+            "reinhard" => Ok(ToneMapping::Reinhard),
+            "aces" => Ok(ToneMapping::Aces),
+            _ => Err(format!("Tone mapping desconocido '{}' (se espera clamp, reinhard o aces)", name)),
+        }
+    }
+}
+
+fn aces(x: f32) -> f32 {
+    let (a, b, c, d, e) = (2.51, 0.03, 2.43, 0.59, 0.14);
+    ((x * (a * x + b)) / (x * (c * x + d) + e)).clamp(0.0, 1.0)
+}
+
+pub fn tone_map(color: Color, exposure: f32, operator: ToneMapping) -> Color {
+    let exposed = color * exposure;
+
+    match operator {
+        ToneMapping::Clamp => Color::rgb(
+            exposed.r.min(1.0),
+            exposed.g.min(1.0),
+            exposed.b.min(1.0),
+        ),
+        ToneMapping::Reinhard => Color::rgb(
+            exposed.r / (1.0 + exposed.r),
+            exposed.g / (1.0 + exposed.g),
+            exposed.b / (1.0 + exposed.b),
+        ),
+        ToneMapping::Aces => Color::rgb(aces(exposed.r), aces(exposed.g), aces(exposed.b)),
+    }
+}
@@ -0,0 +1,89 @@
+use nalgebra_glm::{self as glm, Mat3, Mat4, Vec3, Vec4};
+
+use crate::aabb::Aabb;
+use crate::ray::{Ray, RayCone};
+use crate::ray_intersect::{Intersect, RayIntersect};
+
+// Matriz de escala, luego rotación (grados, en orden x, y, z) y al final traslación
+pub fn compose(translation: Vec3, rotation_degrees: Vec3, scale: Vec3) -> Mat4 {
+    let rotation = glm::rotation(rotation_degrees.z.to_radians(), &Vec3::z_axis())
+        * glm::rotation(rotation_degrees.y.to_radians(), &Vec3::y_axis())
+        * glm::rotation(rotation_degrees.x.to_radians(), &Vec3::x_axis());
+
+    glm::translation(&translation) * rotation * glm::scaling(&scale)
+}
+
+pub fn transform_point(matrix: &Mat4, point: &Vec3) -> Vec3 {
+    (matrix * Vec4::new(point.x, point.y, point.z, 1.0)).xyz()
+}
+
+pub fn transform_vector(matrix: &Mat4, vector: &Vec3) -> Vec3 {
+    (matrix * Vec4::new(vector.x, vector.y, vector.z, 0.0)).xyz()
+}
+
+// Las normales se transforman con la inversa transpuesta para seguir siendo perpendiculares
+pub fn normal_matrix(matrix: &Mat4) -> Mat3 {
+    glm::transpose(&glm::mat4_to_mat3(&glm::inverse(matrix)))
+}
+
+// Objeto con una transformación arbitraria. El rayo se lleva al espacio del objeto sin
+// normalizar la dirección, así la distancia t que devuelve el objeto sirve igual en el mundo.
+pub struct Transformed {
+    object: Box<dyn RayIntersect>,
+    transform: Mat4,
+    inverse: Mat4,
+    normal_matrix: Mat3,
+    scale: f32,  // Escala media, para llevar el cono del rayo al espacio del objeto
+}
+
+impl Transformed {
+    pub fn new(object: Box<dyn RayIntersect>, transform: Mat4) -> Self {
+        Transformed {
+            object,
+            transform,
+            inverse: glm::inverse(&transform),
+            normal_matrix: normal_matrix(&transform),
+            scale: glm::mat4_to_mat3(&transform).determinant().abs().cbrt(),
+        }
+    }
+}
+
+impl RayIntersect for Transformed {
+    fn ray_intersect(&self, ray: &Ray) -> Intersect<'_> {
+        let local_ray = Ray {
+            origin: transform_point(&self.inverse, &ray.origin),
+            direction: transform_vector(&self.inverse, &ray.direction),
+            cone: RayCone::new(ray.cone.width / self.scale, ray.cone.spread / self.scale),
+            ..*ray
+        };
+
+        // object_id y el resto de lo que no depende del espacio quedan como los dejó el objeto
+        let mut intersect = self.object.ray_intersect(&local_ray);
+        if intersect.is_intersecting {
+            intersect.point = ray.at(intersect.distance);
+            intersect.normal = (self.normal_matrix * intersect.normal).normalize();
+            intersect.geometric_normal = (self.normal_matrix * intersect.geometric_normal).normalize();
+            // Las tangentes son direcciones sobre la superficie: se transforman como vectores
+            intersect.tangent = transform_vector(&self.transform, &intersect.tangent);
+            intersect.bitangent = transform_vector(&self.transform, &intersect.bitangent);
+        }
+
+        intersect
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let local = self.object.bounding_box();
+        let mut bounds = Aabb::empty();
+
+        for corner in 0..8 {
+            let point = Vec3::new(
+                if corner & 1 == 0 { local.min.x } else { local.max.x },
+                if corner & 2 == 0 { local.min.y } else { local.max.y },
+                if corner & 4 == 0 { local.min.z } else { local.max.z },
+            );
+            bounds.grow(&transform_point(&self.transform, &point));
+        }
+
+        bounds
+    }
+}
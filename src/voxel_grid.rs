@@ -0,0 +1,269 @@
+use nalgebra_glm::{Vec2, Vec3};
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::cube::{face_texture_coordinates, face_uv_axes};
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::ray_intersect::{Intersect, RayIntersect};
+
+pub const AIR: u16 = 0;
+
+// Mundo de bloques denso: cada celda guarda un id de bloque (0 = aire)
+// y cada id distinto de cero apunta a un material de la paleta
+pub struct VoxelGrid {
+    pub origin: Vec3,  // Esquina mínima de la rejilla
+    pub block_size: f32,
+    pub size: [usize; 3],
+    blocks: Vec<u16>,
+    palette: Vec<Arc<Material>>,
+}
+
+impl VoxelGrid {
+    pub fn new(origin: Vec3, block_size: f32, size: [usize; 3]) -> Self {
+        VoxelGrid {
+            origin,
+            block_size,
+            size,
+            blocks: vec![AIR; size[0] * size[1] * size[2]],
+            palette: Vec::new(),
+        }
+    }
+
+    // Registra un tipo de bloque y devuelve su id; el 0 es el aire, así que caben u16::MAX tipos
+    pub fn add_block_type(&mut self, material: Arc<Material>) -> Result<u16, String> {
+        if self.palette.len() >= u16::MAX as usize {
+            return Err(format!("La rejilla admite a lo sumo {} tipos de bloque", u16::MAX));
+        }
+        self.palette.push(material);
+        Ok(self.palette.len() as u16)
+    }
+
+    fn index(&self, x: usize, y: usize, z: usize) -> usize {
+        (z * self.size[1] + y) * self.size[0] + x
+    }
+
+    pub fn contains(&self, x: usize, y: usize, z: usize) -> bool {
+        x < self.size[0] && y < self.size[1] && z < self.size[2]
+    }
+
+    pub fn get(&self, x: usize, y: usize, z: usize) -> u16 {
+        if !self.contains(x, y, z) {
+            return AIR;
+        }
+        self.blocks[self.index(x, y, z)]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, z: usize, block: u16) {
+        assert!(block as usize <= self.palette.len(), "Bloque {} sin material", block);
+        if self.contains(x, y, z) {
+            let index = self.index(x, y, z);
+            self.blocks[index] = block;
+        }
+    }
+
+    // Llena la región [min, max] (inclusive) con el mismo bloque
+    pub fn fill(&mut self, min: [usize; 3], max: [usize; 3], block: u16) {
+        for z in min[2]..=max[2].min(self.size[2].saturating_sub(1)) {
+            for y in min[1]..=max[1].min(self.size[1].saturating_sub(1)) {
+                for x in min[0]..=max[0].min(self.size[0].saturating_sub(1)) {
+                    self.set(x, y, z, block);
+                }
+            }
+        }
+    }
+
+    fn block_bounds(&self, cell: [usize; 3]) -> (Vec3, Vec3) {
+        let min = self.origin + Vec3::new(cell[0] as f32, cell[1] as f32, cell[2] as f32) * self.block_size;
+        (min, min + Vec3::new(self.block_size, self.block_size, self.block_size))
+    }
+}
+
+impl RayIntersect for VoxelGrid {
+    // Recorrido 3D-DDA (Amanatides–Woo): solo se visitan las celdas que cruza el rayo
+    fn ray_intersect(&self, ray: &Ray) -> Intersect<'_> {
+        let ray_origin = &ray.origin;
+        let ray_direction = &ray.direction;
+        let inv_direction = Vec3::new(1.0 / ray_direction.x, 1.0 / ray_direction.y, 1.0 / ray_direction.z);
+        let bounds = self.bounding_box();
+
+        let t_enter = match bounds.hit(ray_origin, &inv_direction, ray.t_max) {
+            Some(t) => t,
+            None => return Intersect::empty(),
+        };
+
+        // Celda inicial y eje por el que se entró a la rejilla (si el rayo empieza afuera)
+        let start = ray_origin + ray_direction * t_enter;
+        let local = (start - self.origin) / self.block_size;
+        let mut cell = [0isize; 3];
+        let mut step = [0isize; 3];
+        let mut t_next = [f32::INFINITY; 3];
+        let mut t_delta = [f32::INFINITY; 3];
+        let mut entry_axis = None;
+        let mut entry_t = f32::NEG_INFINITY;
+
+        for axis in 0..3 {
+            cell[axis] = (local[axis].floor() as isize).clamp(0, self.size[axis] as isize - 1);
+
+            if ray_direction[axis] > 0.0 {
+                step[axis] = 1;
+                let boundary = self.origin[axis] + (cell[axis] + 1) as f32 * self.block_size;
+                t_next[axis] = (boundary - ray_origin[axis]) * inv_direction[axis];
+                t_delta[axis] = self.block_size * inv_direction[axis];
+            } else if ray_direction[axis] < 0.0 {
+                step[axis] = -1;
+                let boundary = self.origin[axis] + cell[axis] as f32 * self.block_size;
+                t_next[axis] = (boundary - ray_origin[axis]) * inv_direction[axis];
+                t_delta[axis] = -self.block_size * inv_direction[axis];
+            }
+
+            // La cara de entrada es la del plano que el rayo cruza último
+            if t_enter > 0.0 && step[axis] != 0 {
+                let plane = if step[axis] > 0 { bounds.min[axis] } else { bounds.max[axis] };
+                let t_plane = (plane - ray_origin[axis]) * inv_direction[axis];
+                if t_plane > entry_t {
+                    entry_t = t_plane;
+                    entry_axis = Some(axis);
+                }
+            }
+        }
+
+        let mut t = t_enter;
+
+        loop {
+            let block = self.get(cell[0] as usize, cell[1] as usize, cell[2] as usize);
+
+            // Eje cuyo siguiente borde está más cerca: por ahí sale el rayo de la celda
+            let axis = if t_next[0] < t_next[1] {
+                if t_next[0] < t_next[2] { 0 } else { 2 }
+            } else if t_next[1] < t_next[2] {
+                1
+            } else {
+                2
+            };
+
+            if block != AIR {
+                let material = &self.palette[block as usize - 1];
+                let (min, max) = self.block_bounds([cell[0] as usize, cell[1] as usize, cell[2] as usize]);
+
+                if let Some(entry) = entry_axis {
+                    let mut normal = Vec3::zeros();
+                    normal[entry] = -step[entry] as f32;
+                    let face_index = entry * 2 + if normal[entry] > 0.0 { 1 } else { 0 };
+                    let point = ray_origin + ray_direction * t;
+
+                    let (u, v) = face_texture_coordinates(face_index, &point, &min, &max);
+                    let footprint = ray.cone.uv_footprint(t, ray_direction, &normal, self.block_size);
+                    if let Some(hit) = Intersect::surface(material, point, normal, t, face_index, Vec2::new(u, v), footprint) {
+                        let (tangent, bitangent) = face_uv_axes(face_index);
+                        return hit.with_tangents(tangent, bitangent);
+                    }
+                }
+
+                // Cara por la que sale el rayo: se ve su interior si el texel de entrada era
+                // transparente, y su exterior si el rayo nació dentro del bloque (refracción
+                // o sombra). Los rayos que descartan caras traseras ignoran ese bloque.
+                let inward = entry_axis.is_some();
+                let t_exit = t_next[axis];
+                let mut normal = Vec3::zeros();
+                normal[axis] = if inward { -step[axis] } else { step[axis] } as f32;
+                let face_index = axis * 2 + if step[axis] > 0 { 1 } else { 0 };
+                let point = ray_origin + ray_direction * t_exit;
+
+                if t_exit > ray.t_min && t_exit <= ray.t_max && !ray.is_back_face(&normal) {
+                    let (u, v) = face_texture_coordinates(face_index, &point, &min, &max);
+                    let footprint = ray.cone.uv_footprint(t_exit, ray_direction, &normal, self.block_size);
+                    if let Some(hit) = Intersect::surface(material, point, normal, t_exit, face_index, Vec2::new(u, v), footprint) {
+                        let (tangent, bitangent) = face_uv_axes(face_index);
+                        return hit.with_tangents(tangent, bitangent);
+                    }
+                }
+            }
+
+            cell[axis] += step[axis];
+            if cell[axis] < 0 || cell[axis] >= self.size[axis] as isize {
+                return Intersect::empty();
+            }
+
+            // Más allá de t_max no cuenta ningún impacto (p. ej. la luz de un rayo de sombra)
+            t = t_next[axis];
+            if t > ray.t_max {
+                return Intersect::empty();
+            }
+            t_next[axis] += t_delta[axis];
+            entry_axis = Some(axis);
+        }
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let extent = Vec3::new(self.size[0] as f32, self.size[1] as f32, self.size[2] as f32) * self.block_size;
+        Aabb::new(self.origin, self.origin + extent)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra_glm::Vec3;
+    use std::sync::Arc;
+
+    use super::VoxelGrid;
+    use crate::material::Material;
+    use crate::ray::{Ray, RayCone, RayKind};
+    use crate::ray_intersect::RayIntersect;
+
+    // Rejilla de 4x4x4 bloques de lado 1 desde el origen, con un solo bloque en (1, 1, 1)
+    fn single_block() -> VoxelGrid {
+        let mut grid = VoxelGrid::new(Vec3::zeros(), 1.0, [4, 4, 4]);
+        let block = grid.add_block_type(Arc::new(Material::black())).unwrap();
+        grid.set(1, 1, 1, block);
+        grid
+    }
+
+    fn ray(origin: Vec3, direction: Vec3) -> Ray {
+        Ray::new(origin, direction, RayKind::Primary, RayCone::new(0.0, 0.0))
+    }
+
+    #[test]
+    fn hits_the_entry_face_from_outside() {
+        let grid = single_block();
+        let hit = grid.ray_intersect(&ray(Vec3::new(1.5, 1.5, -5.0), Vec3::z()));
+
+        assert!(hit.is_intersecting);
+        assert!((hit.distance - 6.0).abs() < 1e-5);
+        assert_eq!(hit.normal, Vec3::new(0.0, 0.0, -1.0));
+        assert_eq!(hit.face_index, 4);
+        assert!((hit.point - Vec3::new(1.5, 1.5, 1.0)).magnitude() < 1e-5);
+    }
+
+    #[test]
+    fn hits_from_a_ray_that_starts_inside_the_grid() {
+        let grid = single_block();
+        let hit = grid.ray_intersect(&ray(Vec3::new(3.5, 1.5, 1.5), -Vec3::x()));
+
+        assert!(hit.is_intersecting);
+        assert!((hit.distance - 1.5).abs() < 1e-5);
+        assert_eq!(hit.normal, Vec3::x());
+        assert_eq!(hit.face_index, 1);
+    }
+
+    #[test]
+    fn misses_when_no_block_is_crossed() {
+        let grid = single_block();
+
+        // Cruza la rejilla por una columna vacía
+        assert!(!grid.ray_intersect(&ray(Vec3::new(2.5, 1.5, -5.0), Vec3::z())).is_intersecting);
+        // Apunta al bloque pero en sentido contrario
+        assert!(!grid.ray_intersect(&ray(Vec3::new(1.5, 1.5, -5.0), -Vec3::z())).is_intersecting);
+        // Ni siquiera toca la rejilla
+        assert!(!grid.ray_intersect(&ray(Vec3::new(-1.0, 10.0, 0.0), Vec3::x())).is_intersecting);
+    }
+
+    #[test]
+    fn stops_at_t_max() {
+        let grid = single_block();
+        let toward_block = ray(Vec3::new(1.5, 1.5, -5.0), Vec3::z());
+
+        assert!(!grid.ray_intersect(&toward_block.with_max_distance(5.5)).is_intersecting);
+        assert!(grid.ray_intersect(&toward_block.with_max_distance(6.5)).is_intersecting);
+    }
+}